@@ -1,7 +1,9 @@
 // Copyright (c) 2022 Milen Dzhumerov
 use crate::binary::{
     byte_decoder::ByteDecoder,
+    byte_encoder::ByteEncoder,
     entry::Entry,
+    error::{BucketError, BucketErrorKind, StringError},
     header::DataHeader,
     types::{BucketCountType, StringSectionOffsetType, STRING_SECTION_OFFSET_RESERVED},
     utility::{ByteSwappable, Packable},
@@ -14,26 +16,10 @@ use crate::binary::{
 /// Offsets are relative to the string section offset defined
 /// in the headermap data header.
 #[derive(Debug)]
-struct Bucket {
-    key_offset: StringSectionOffsetType,
-    prefix_offset: StringSectionOffsetType,
-    suffix_offset: StringSectionOffsetType,
-}
-
-#[derive(thiserror::Error, Debug)]
-enum BucketParseError {
-    #[error("Could not parse string offset for bucket at index `{0}`")]
-    OffsetParseError(BucketCountType),
-    #[error("Invalid string offset (`{0}`), overlaps the preamble section")]
-    InvalidStringOffsetOverlapsPreambleSection(usize),
-    #[error("Invalid string offset (`{0}`), out of bounds")]
-    InvalidStringOffsetOutOfBounds(usize),
-    #[error("Invalid string offset (`{0}`), no NULL terminating byte found")]
-    NoStringNullTerminatingByteFound(usize),
-    #[error("Invalid string offset (`{0}`), UTF-8 parsing error: {1}")]
-    InvalidUtf8String(usize, std::str::Utf8Error),
-    #[error("Invalid string offset (`{0}`), unknown internal error")]
-    InternalError(usize),
+pub struct Bucket {
+    pub key_offset: StringSectionOffsetType,
+    pub prefix_offset: StringSectionOffsetType,
+    pub suffix_offset: StringSectionOffsetType,
 }
 
 impl Packable for Bucket {
@@ -43,39 +29,35 @@ impl Packable for Bucket {
 }
 
 /// Tries to find a NULL-terminated string at a particular string offset.
-fn get_string_slice_at_offset<'a>(
+pub(crate) fn get_string_slice_at_offset<'a>(
     bytes: &'a [u8],
     data_header: &DataHeader,
+    bucket_index: BucketCountType,
     relative_string_start_offset: StringSectionOffsetType,
-) -> anyhow::Result<&'a str> {
-    let absolute_tring_start_offset =
-        (data_header.string_section_offset + relative_string_start_offset) as usize;
-    let headermap_preamble_size = DataHeader::packed_size() + Bucket::packed_size();
+) -> Result<&'a str, BucketError> {
+    let absolute_string_start_offset =
+        (data_header.string_section_offset as usize) + (relative_string_start_offset as usize);
+    let string_error = |string_error| BucketError {
+        bucket_index,
+        offset: absolute_string_start_offset,
+        kind: BucketErrorKind::InvalidString(string_error),
+    };
 
-    if absolute_tring_start_offset < headermap_preamble_size {
-        anyhow::bail!(
-            BucketParseError::InvalidStringOffsetOverlapsPreambleSection(
-                absolute_tring_start_offset
-            )
-        );
+    let headermap_preamble_size = DataHeader::packed_size() + Bucket::packed_size();
+    if absolute_string_start_offset < headermap_preamble_size {
+        return Err(string_error(StringError::OverlapsPreambleSection));
     }
 
-    let string_search_bytes = bytes.get(absolute_tring_start_offset..).ok_or(
-        BucketParseError::InvalidStringOffsetOutOfBounds(absolute_tring_start_offset),
-    )?;
-    let null_byte_offset = string_search_bytes.iter().position(|&x| x == 0x0).ok_or(
-        BucketParseError::NoStringNullTerminatingByteFound(absolute_tring_start_offset),
-    )?;
-
-    let string_bytes = string_search_bytes
-        .get(..null_byte_offset)
-        .ok_or(BucketParseError::InternalError(absolute_tring_start_offset))?;
-    std::str::from_utf8(string_bytes).map_err(|utf8_error| {
-        anyhow::Error::new(BucketParseError::InvalidUtf8String(
-            absolute_tring_start_offset,
-            utf8_error,
-        ))
-    })
+    let string_search_bytes = bytes
+        .get(absolute_string_start_offset..)
+        .ok_or_else(|| string_error(StringError::OutOfBounds))?;
+    let null_byte_offset = string_search_bytes
+        .iter()
+        .position(|&x| x == 0x0)
+        .ok_or_else(|| string_error(StringError::NoNullTerminatingByteFound))?;
+
+    std::str::from_utf8(&string_search_bytes[..null_byte_offset])
+        .map_err(|utf8_error| string_error(StringError::InvalidUtf8(utf8_error)))
 }
 
 /// Returns the headermap entry for a particular bucket. If the bucket is empty, it returns `None`.
@@ -85,78 +67,121 @@ pub fn parse_entry_at_bucket_index<'a>(
     data_header: &DataHeader,
     bucket_index: BucketCountType,
     swap_bytes: bool,
-) -> anyhow::Result<Option<Entry<'a>>> {
+) -> Result<Option<Entry<'a>>, BucketError> {
     let maybe_bucket = Bucket::new_at_index(bytes, bucket_index, swap_bytes)?;
     maybe_bucket
-        .map(|bucket| bucket.to_entry(bytes, data_header))
+        .map(|bucket| {
+            bucket
+                .to_entry(bytes, data_header, bucket_index)
+                .map_err(|(_, error)| error)
+        })
         .transpose()
 }
 
+/// The contents of a bucket, as far as Clang reads them.
+pub(crate) enum BucketState<'a> {
+    Empty,
+    Occupied(Entry<'a>),
+    /// `key` is `None` if the key cannot be read.
+    Corrupt {
+        key: Option<&'a str>,
+        error: BucketError,
+    },
+}
+
+/// Like `parse_entry_at_bucket_index()`, but returns the key of a corrupt
+/// bucket if it can be read, Clang still compares it during lookups.
+pub(crate) fn parse_bucket_state_at_index<'a>(
+    bytes: &'a [u8],
+    data_header: &DataHeader,
+    bucket_index: BucketCountType,
+    swap_bytes: bool,
+) -> BucketState<'a> {
+    let bucket = match Bucket::new_at_index(bytes, bucket_index, swap_bytes) {
+        Ok(Some(bucket)) => bucket,
+        Ok(None) => return BucketState::Empty,
+        Err(error) => return BucketState::Corrupt { key: None, error },
+    };
+    match bucket.to_entry(bytes, data_header, bucket_index) {
+        Ok(entry) => BucketState::Occupied(entry),
+        Err((key, error)) => BucketState::Corrupt { key, error },
+    }
+}
+
 /// Parses a `StringSectionOffsetType` at a particular `bucket_index`.
-/// If it encounters a reserved offset (meaning empty), it returns
-/// `Option::None`.
 fn parse_string_section_offset(
     decoder: &mut ByteDecoder,
     bucket_index: BucketCountType,
     swap_bytes: bool,
-) -> anyhow::Result<Option<StringSectionOffsetType>> {
-    let offset = decoder
+) -> Result<StringSectionOffsetType, BucketError> {
+    Ok(decoder
         .advance::<StringSectionOffsetType>()
-        .ok_or(BucketParseError::OffsetParseError(bucket_index))?
-        .swap_bytes_if(swap_bytes);
-
-    if offset == STRING_SECTION_OFFSET_RESERVED {
-        Ok(None)
-    } else {
-        Ok(Some(offset))
-    }
+        .ok_or(BucketError {
+            bucket_index,
+            offset: decoder.offset,
+            kind: BucketErrorKind::OffsetParseError,
+        })?
+        .swap_bytes_if(swap_bytes))
 }
 
 impl Bucket {
-    /// Returns a `Bucket` if all offsets contains values.
-    fn try_new(
-        maybe_key_offset: Option<StringSectionOffsetType>,
-        maybe_prefix_offset: Option<StringSectionOffsetType>,
-        maybe_suffix_offset: Option<StringSectionOffsetType>,
-    ) -> Option<Bucket> {
-        let key_offset = maybe_key_offset?;
-        let prefix_offset = maybe_prefix_offset?;
-        let suffix_offset = maybe_suffix_offset?;
-        Some(Bucket {
-            key_offset,
-            prefix_offset,
-            suffix_offset,
-        })
+    /// An empty bucket, all offsets are reserved.
+    pub const EMPTY: Bucket = Bucket {
+        key_offset: STRING_SECTION_OFFSET_RESERVED,
+        prefix_offset: STRING_SECTION_OFFSET_RESERVED,
+        suffix_offset: STRING_SECTION_OFFSET_RESERVED,
+    };
+
+    /// Encodes the string offsets in native byte order.
+    pub fn encode(&self, encoder: &mut ByteEncoder) {
+        encoder.append(self.key_offset);
+        encoder.append(self.prefix_offset);
+        encoder.append(self.suffix_offset);
     }
 
-    /// Parses they string offsets for a particular `bucket_index`
-    /// and returns a `Bucket` if all offsets contain non-reserved values.
-    fn new_at_index(
+    /// Parses they string offsets for a particular `bucket_index` and
+    /// returns a `Bucket` unless the key offset is reserved. Like in Clang,
+    /// only the key offset marks a bucket as empty, reserved prefix and
+    /// suffix offsets point to the start of the string section.
+    pub(crate) fn new_at_index(
         bytes: &[u8],
         bucket_index: BucketCountType,
         swap_bytes: bool,
-    ) -> anyhow::Result<Option<Bucket>> {
+    ) -> Result<Option<Bucket>, BucketError> {
         let offset = DataHeader::packed_size() + (bucket_index as usize) * Bucket::packed_size();
         let mut decoder = ByteDecoder { bytes, offset };
 
-        let maybe_key_offset = parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
-        let maybe_prefix_offset =
-            parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
-        let maybe_suffix_offset =
-            parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
-
-        Ok(Bucket::try_new(
-            maybe_key_offset,
-            maybe_prefix_offset,
-            maybe_suffix_offset,
-        ))
+        let key_offset = parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
+        let prefix_offset = parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
+        let suffix_offset = parse_string_section_offset(&mut decoder, bucket_index, swap_bytes)?;
+
+        if key_offset == STRING_SECTION_OFFSET_RESERVED {
+            return Ok(None);
+        }
+
+        Ok(Some(Bucket {
+            key_offset,
+            prefix_offset,
+            suffix_offset,
+        }))
     }
 
     /// Converts a `Bucket` into an `Entry` by trying to convert each string offset into a string slice.
-    fn to_entry<'a>(&self, bytes: &'a [u8], data_header: &DataHeader) -> anyhow::Result<Entry<'a>> {
-        let key = get_string_slice_at_offset(bytes, data_header, self.key_offset)?;
-        let prefix = get_string_slice_at_offset(bytes, data_header, self.prefix_offset)?;
-        let suffix = get_string_slice_at_offset(bytes, data_header, self.suffix_offset)?;
+    /// If the prefix or suffix cannot be converted, the error comes with the key.
+    fn to_entry<'a>(
+        &self,
+        bytes: &'a [u8],
+        data_header: &DataHeader,
+        bucket_index: BucketCountType,
+    ) -> Result<Entry<'a>, (Option<&'a str>, BucketError)> {
+        let key = get_string_slice_at_offset(bytes, data_header, bucket_index, self.key_offset)
+            .map_err(|error| (None, error))?;
+        let get_target_string = |offset| {
+            get_string_slice_at_offset(bytes, data_header, bucket_index, offset)
+                .map_err(|error| (Some(key), error))
+        };
+        let prefix = get_target_string(self.prefix_offset)?;
+        let suffix = get_target_string(self.suffix_offset)?;
 
         Ok(Entry {
             key,
@@ -0,0 +1,35 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::utility::*;
+
+#[derive(Default)]
+pub struct ByteEncoder {
+    pub bytes: Vec<u8>,
+}
+
+impl ByteEncoder {
+    pub fn append<T: EncodablePrimitive>(&mut self, value: T) {
+        value.encode_to_bytes(&mut self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::byte_decoder::ByteDecoder;
+
+    #[test]
+    fn test_encode() {
+        let mut byte_encoder = ByteEncoder::default();
+        byte_encoder.append(0xABCDu16);
+        byte_encoder.append(0x01234567u32);
+        assert_eq!(byte_encoder.bytes.len(), 6);
+
+        let mut byte_decoder = ByteDecoder {
+            bytes: &byte_encoder.bytes,
+            offset: 0,
+        };
+        assert_eq!(byte_decoder.advance::<u16>(), Some(0xABCD));
+        assert_eq!(byte_decoder.advance::<u32>(), Some(0x01234567));
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::BTreeMap;
+
+use crate::binary::{
+    entry::{Entry, OwnedEntry},
+    error::Result,
+    json,
+};
+
+/// An entry whose key exists in both headermaps but maps to a different
+/// prefix and/or suffix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedEntry {
+    pub old: OwnedEntry,
+    pub new: OwnedEntry,
+}
+
+impl ChangedEntry {
+    pub fn prefix_changed(&self) -> bool {
+        self.old.prefix != self.new.prefix
+    }
+
+    pub fn suffix_changed(&self) -> bool {
+        self.old.suffix != self.new.suffix
+    }
+}
+
+/// The differences between two headermaps, compared by key rather than
+/// by byte layout. All lists are sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMapDiff {
+    /// Entries whose key only exists in the new headermap.
+    pub added: Vec<OwnedEntry>,
+    /// Entries whose key only exists in the old headermap.
+    pub removed: Vec<OwnedEntry>,
+    pub changed: Vec<ChangedEntry>,
+}
+
+impl HeaderMapDiff {
+    /// Returns `true` if both headermaps contain the same entries.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// One line per difference, prefixed by `+` (added), `-` (removed)
+    /// or `~` (changed).
+    Text,
+    /// `{"added": {...}, "removed": {...}, "changed": {...}}`
+    Json,
+}
+
+/// Compares the entries of two headermaps by key. Keys are compared
+/// exactly, so a key whose case changed is reported as removed and added.
+pub fn diff_entries(old_entries: &[Entry], new_entries: &[Entry]) -> HeaderMapDiff {
+    let old_by_key: BTreeMap<_, _> = old_entries.iter().map(|entry| (entry.key, entry)).collect();
+    let new_by_key: BTreeMap<_, _> = new_entries.iter().map(|entry| (entry.key, entry)).collect();
+
+    let mut diff = HeaderMapDiff::default();
+    for (key, old_entry) in &old_by_key {
+        match new_by_key.get(key) {
+            None => diff.removed.push(old_entry.to_owned_entry()),
+            Some(new_entry) if new_entry != old_entry => diff.changed.push(ChangedEntry {
+                old: old_entry.to_owned_entry(),
+                new: new_entry.to_owned_entry(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    diff.added = new_by_key
+        .iter()
+        .filter(|(key, _)| !old_by_key.contains_key(*key))
+        .map(|(_, new_entry)| new_entry.to_owned_entry())
+        .collect();
+
+    diff
+}
+
+/// Prints the differences in the given format.
+pub fn print_diff<W>(writer: &mut W, diff: &HeaderMapDiff, format: DiffFormat) -> Result<()>
+where
+    W: std::io::Write,
+{
+    match format {
+        DiffFormat::Text => print_text_diff(writer, diff),
+        DiffFormat::Json => json::print_json_diff(writer, diff, 4),
+    }
+}
+
+fn print_text_diff<W>(writer: &mut W, diff: &HeaderMapDiff) -> Result<()>
+where
+    W: std::io::Write,
+{
+    for entry in &diff.removed {
+        writeln!(writer, "- {}", entry)?;
+    }
+    for entry in &diff.added {
+        writeln!(writer, "+ {}", entry)?;
+    }
+    for changed_entry in &diff.changed {
+        let changed_parts = match (
+            changed_entry.prefix_changed(),
+            changed_entry.suffix_changed(),
+        ) {
+            (true, true) => "prefix and suffix",
+            (true, false) => "prefix",
+            _ => "suffix",
+        };
+        writeln!(
+            writer,
+            "~ {} -> {}{} => {}{} ({} changed)",
+            changed_entry.old.key,
+            changed_entry.old.prefix,
+            changed_entry.old.suffix,
+            changed_entry.new.prefix,
+            changed_entry.new.suffix,
+            changed_parts
+        )?;
+    }
+
+    Ok(())
+}
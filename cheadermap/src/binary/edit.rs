@@ -0,0 +1,415 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::HashMap;
+
+use crate::binary::{
+    bucket::Bucket,
+    entries::entries,
+    entry::OwnedEntry,
+    error::Result,
+    hash::hash_key,
+    header::{
+        self, parse_header, DataHeader, MAX_VALUE_LENGTH_FIELD_OFFSET, STRING_COUNT_FIELD_OFFSET,
+    },
+    types::*,
+    utility::{ByteSwappable, Packable},
+    writer::{build_headermap, HeaderMapWriteError, MAX_LOAD_FACTOR},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum EditError {
+    #[error("Key not found, `{0}`")]
+    KeyNotFound(String),
+    #[error("Key already exists (keys are case-insensitive), found `{0}`")]
+    KeyExists(String),
+    #[error("Invalid edit script line {0}: `{1}`")]
+    InvalidScriptLine(usize, String),
+}
+
+/// An edit of a headermap. Like Clang, keys are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOperation {
+    /// Adds an entry, fails if the key exists.
+    Add(OwnedEntry),
+    /// Adds an entry or replaces the entry with the same key.
+    Set(OwnedEntry),
+    /// Replaces the entry with the same key, fails if the key does not exist.
+    Replace(OwnedEntry),
+    /// Removes the entry with the key, fails if the key does not exist.
+    Remove(String),
+    /// Changes the key of an entry, fails if the key does not exist
+    /// or if the new key belongs to another entry.
+    Rename { from: String, to: String },
+}
+
+/// How the edited headermap was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The bucket table got updated in place. Strings which did not exist
+    /// in the string section got appended to it.
+    InPlace,
+    /// The bucket table did not have enough capacity, so the headermap
+    /// got rebuilt from scratch.
+    Rebuilt,
+}
+
+/// Parses an edit script with one operation per line. Empty lines and
+/// lines starting with `#` are ignored. The supported operations are:
+///
+/// ```text
+/// add <key> <target path>
+/// set <key> <target path>
+/// replace <key> <target path>
+/// remove <key>
+/// rename <key> <new key>
+/// ```
+///
+/// `rm` and `mv` are accepted as aliases of `remove` and `rename`. The
+/// target path extends to the end of the line and gets split into prefix
+/// and suffix by `OwnedEntry::from_target_path`.
+pub fn parse_edit_script(bytes: &[u8]) -> Result<Vec<EditOperation>> {
+    let contents = String::from_utf8_lossy(bytes);
+    let mut operations = Vec::new();
+    for (line_index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid_line = || EditError::InvalidScriptLine(line_index + 1, line.to_owned());
+        let (operation_name, rest) = split_first_word(line);
+        let (key, argument) = split_first_word(rest);
+        if key.is_empty() {
+            return Err(invalid_line().into());
+        }
+        let argument = Some(argument).filter(|argument| !argument.is_empty());
+        let entry = || {
+            argument
+                .map(|target_path| OwnedEntry::from_target_path(key, target_path))
+                .ok_or_else(invalid_line)
+        };
+
+        let operation = match (operation_name, argument) {
+            ("add", _) => EditOperation::Add(entry()?),
+            ("set", _) => EditOperation::Set(entry()?),
+            ("replace", _) => EditOperation::Replace(entry()?),
+            ("remove" | "rm", None) => EditOperation::Remove(key.to_owned()),
+            ("rename" | "mv", Some(to)) if !to.contains(char::is_whitespace) => {
+                EditOperation::Rename {
+                    from: key.to_owned(),
+                    to: to.to_owned(),
+                }
+            }
+            _ => return Err(invalid_line().into()),
+        };
+        operations.push(operation);
+    }
+
+    Ok(operations)
+}
+
+/// Splits `string` at the first run of whitespace. The second part is
+/// empty if there's no whitespace.
+fn split_first_word(string: &str) -> (&str, &str) {
+    match string.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (string, ""),
+    }
+}
+
+/// An occupied bucket. `offsets` are the key, prefix and suffix offsets
+/// of the original bucket or `None` if the strings changed.
+struct Slot {
+    entry: OwnedEntry,
+    offsets: Option<[StringSectionOffsetType; 3]>,
+}
+
+impl Slot {
+    fn new(entry: OwnedEntry) -> Slot {
+        Slot {
+            entry,
+            offsets: None,
+        }
+    }
+}
+
+/// A linear probing bucket table, mirroring the headermap's one.
+struct BucketTable {
+    slots: Vec<Option<Slot>>,
+    len: usize,
+    /// Set if the table had to grow, so it no longer matches the headermap's.
+    grown: bool,
+}
+
+impl BucketTable {
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    /// Returns the index of the slot with the key, probing like Clang.
+    fn find(&self, key: &str) -> Option<usize> {
+        let start_index = hash_key(key) as usize & self.mask();
+        for probe_count in 0..self.slots.len() {
+            let index = (start_index + probe_count) & self.mask();
+            match &self.slots[index] {
+                Some(slot) if slot.entry.key.eq_ignore_ascii_case(key) => return Some(index),
+                Some(_) => continue,
+                None => return None,
+            }
+        }
+        None
+    }
+
+    /// Inserts a slot whose key does not exist yet.
+    fn insert(&mut self, slot: Slot) {
+        let (numerator, denominator) = MAX_LOAD_FACTOR;
+        if (self.len + 1) * denominator > self.slots.len() * numerator {
+            self.grow();
+        }
+        self.insert_without_growing(slot);
+    }
+
+    fn insert_without_growing(&mut self, slot: Slot) {
+        let mut index = hash_key(&slot.entry.key) as usize & self.mask();
+        while self.slots[index].is_some() {
+            index = (index + 1) & self.mask();
+        }
+        self.slots[index] = Some(slot);
+        self.len += 1;
+    }
+
+    /// Removes the slot at `index`. Following slots of the same cluster
+    /// get reinserted, so lookups do not stop early at the new hole.
+    fn remove(&mut self, index: usize) -> Slot {
+        let removed_slot = self.slots[index].take().expect("slot is occupied");
+        self.len -= 1;
+
+        let mut next_index = (index + 1) & self.mask();
+        while let Some(slot) = self.slots[next_index].take() {
+            self.len -= 1;
+            self.insert_without_growing(slot);
+            next_index = (next_index + 1) & self.mask();
+        }
+
+        removed_slot
+    }
+
+    fn grow(&mut self) {
+        let slots = std::mem::take(&mut self.slots);
+        self.slots = (0..slots.len() * 2).map(|_| None).collect();
+        self.len = 0;
+        self.grown = true;
+        for slot in slots.into_iter().flatten() {
+            self.insert_without_growing(slot);
+        }
+    }
+
+    fn apply(&mut self, operation: &EditOperation) -> std::result::Result<(), EditError> {
+        match operation {
+            EditOperation::Add(entry) => match self.find(&entry.key) {
+                Some(_) => return Err(EditError::KeyExists(entry.key.clone())),
+                None => self.insert(Slot::new(entry.clone())),
+            },
+            EditOperation::Set(entry) => match self.find(&entry.key) {
+                Some(index) => self.slots[index] = Some(Slot::new(entry.clone())),
+                None => self.insert(Slot::new(entry.clone())),
+            },
+            EditOperation::Replace(entry) => match self.find(&entry.key) {
+                Some(index) => self.slots[index] = Some(Slot::new(entry.clone())),
+                None => return Err(EditError::KeyNotFound(entry.key.clone())),
+            },
+            EditOperation::Remove(key) => match self.find(key) {
+                Some(index) => {
+                    self.remove(index);
+                }
+                None => return Err(EditError::KeyNotFound(key.clone())),
+            },
+            EditOperation::Rename { from, to } => {
+                let index = self
+                    .find(from)
+                    .ok_or_else(|| EditError::KeyNotFound(from.clone()))?;
+                if matches!(self.find(to), Some(to_index) if to_index != index) {
+                    return Err(EditError::KeyExists(to.clone()));
+                }
+
+                let slot = self.remove(index);
+                self.insert(Slot::new(OwnedEntry {
+                    key: to.clone(),
+                    ..slot.entry
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies `operations` in order. If any operation fails, no changes are
+/// made. The bucket table gets updated in place as long as it stays at
+/// most 1/3 full, the same as for `HeaderMapBuilder`, otherwise the
+/// headermap gets rebuilt.
+///
+/// Fails if the bucket table does not fit in `bytes` or if the headermap
+/// contains invalid buckets, use `repair_headermap` for damaged headermaps.
+pub fn edit_headermap(
+    bytes: &[u8],
+    operations: &[EditOperation],
+) -> Result<(Vec<u8>, EditOutcome)> {
+    let parse_result = parse_header(bytes)?;
+    let header = &parse_result.header;
+    let swap_bytes = parse_result.swap_bytes;
+
+    header::check_bucket_table_bounds(header, bytes)?;
+
+    let mut table = BucketTable {
+        slots: (0..header.bucket_count).map(|_| None).collect(),
+        len: 0,
+        grown: false,
+    };
+    for entry_result in entries(bytes)? {
+        let (bucket_index, entry) = entry_result?;
+        let bucket =
+            Bucket::new_at_index(bytes, bucket_index, swap_bytes)?.expect("bucket is occupied");
+        table.slots[bucket_index as usize] = Some(Slot {
+            entry: entry.to_owned_entry(),
+            offsets: Some([
+                bucket.key_offset,
+                bucket.prefix_offset,
+                bucket.suffix_offset,
+            ]),
+        });
+        table.len += 1;
+    }
+
+    for operation in operations {
+        table.apply(operation)?;
+    }
+
+    if table.grown {
+        let entries = table
+            .slots
+            .iter()
+            .flatten()
+            .map(|slot| slot.entry.as_entry());
+        return Ok((build_headermap(entries)?, EditOutcome::Rebuilt));
+    }
+
+    let mut edited_bytes = bytes.to_vec();
+    let mut strings = AppendableStringSection::new(&edited_bytes, header);
+    let mut max_value_length = 0;
+    for (bucket_index, slot) in table.slots.iter().enumerate() {
+        let offsets = match slot {
+            None => [STRING_SECTION_OFFSET_RESERVED; 3],
+            Some(slot) => {
+                let entry = &slot.entry;
+                max_value_length = max_value_length.max(entry.prefix.len() + entry.suffix.len());
+                match slot.offsets {
+                    Some(offsets) => offsets,
+                    None => [
+                        strings.offset(&mut edited_bytes, &entry.key)?,
+                        strings.offset(&mut edited_bytes, &entry.prefix)?,
+                        strings.offset(&mut edited_bytes, &entry.suffix)?,
+                    ],
+                }
+            }
+        };
+
+        let bucket_offset = DataHeader::packed_size() + bucket_index * Bucket::packed_size();
+        for (index, offset) in offsets.into_iter().enumerate() {
+            let field_offset =
+                bucket_offset + index * std::mem::size_of::<StringSectionOffsetType>();
+            write_u32(&mut edited_bytes, field_offset, offset, swap_bytes);
+        }
+    }
+
+    // Keep the header consistent with the entries, the same way
+    // `validate_headermap` checks it.
+    let entry_count = StringCountType::try_from(table.len).unwrap_or(StringCountType::MAX);
+    let max_string_count = entry_count.saturating_mul(3).saturating_add(1);
+    if header.string_count < entry_count || header.string_count > max_string_count {
+        write_u32(
+            &mut edited_bytes,
+            STRING_COUNT_FIELD_OFFSET,
+            entry_count,
+            swap_bytes,
+        );
+    }
+    let max_value_length =
+        MaxValueLength::try_from(max_value_length).unwrap_or(MaxValueLength::MAX);
+    if header.max_value_length != 0 && header.max_value_length != max_value_length {
+        write_u32(
+            &mut edited_bytes,
+            MAX_VALUE_LENGTH_FIELD_OFFSET,
+            max_value_length,
+            swap_bytes,
+        );
+    }
+
+    Ok((edited_bytes, EditOutcome::InPlace))
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32, swap_bytes: bool) {
+    bytes[offset..offset + std::mem::size_of::<u32>()]
+        .copy_from_slice(&value.swap_bytes_if(swap_bytes).to_ne_bytes());
+}
+
+/// The string section of an existing headermap. Existing strings get
+/// reused, new ones get appended to the end of the file (which is the
+/// end of the string section).
+struct AppendableStringSection {
+    string_section_offset: usize,
+    offsets: HashMap<String, StringSectionOffsetType>,
+}
+
+impl AppendableStringSection {
+    fn new(bytes: &[u8], header: &DataHeader) -> AppendableStringSection {
+        let string_section_offset = header.string_section_offset as usize;
+        let mut offsets = HashMap::new();
+        let mut string_start = 0;
+        for (index, byte) in bytes[string_section_offset..].iter().enumerate() {
+            if *byte != 0x0 {
+                continue;
+            }
+            let string_bytes =
+                &bytes[string_section_offset + string_start..][..index - string_start];
+            let is_reserved_offset = string_start == STRING_SECTION_OFFSET_RESERVED as usize;
+            if let (false, Ok(string), Ok(offset)) = (
+                is_reserved_offset,
+                std::str::from_utf8(string_bytes),
+                StringSectionOffsetType::try_from(string_start),
+            ) {
+                offsets.entry(string.to_owned()).or_insert(offset);
+            }
+            string_start = index + 1;
+        }
+
+        AppendableStringSection {
+            string_section_offset,
+            offsets,
+        }
+    }
+
+    fn offset(
+        &mut self,
+        bytes: &mut Vec<u8>,
+        string: &str,
+    ) -> std::result::Result<StringSectionOffsetType, HeaderMapWriteError> {
+        if let Some(offset) = self.offsets.get(string) {
+            return Ok(*offset);
+        }
+        if string.as_bytes().contains(&0x0) {
+            return Err(HeaderMapWriteError::StringContainsNullByte(
+                string.to_owned(),
+            ));
+        }
+
+        let relative_offset = bytes.len() - self.string_section_offset;
+        let offset = StringSectionOffsetType::try_from(relative_offset)
+            .map_err(|_| HeaderMapWriteError::StringSectionTooLarge(relative_offset))?;
+        bytes.extend_from_slice(string.as_bytes());
+        bytes.push(0x0);
+        self.offsets.insert(string.to_owned(), offset);
+
+        Ok(offset)
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    bucket,
+    entry::Entry,
+    error::{BucketError, BucketErrorKind, Result},
+    header::{self, DataHeaderParseResult},
+    types::BucketCountType,
+};
+
+/// Returns an iterator over the non-empty buckets of the headermap, see
+/// `Entries`. Returns an error if the header cannot be parsed.
+pub fn entries(bytes: &[u8]) -> Result<Entries<'_>> {
+    let parse_result = header::parse_header(bytes)?;
+    Ok(Entries {
+        bytes,
+        parse_result,
+        bucket_index: 0,
+    })
+}
+
+/// Iterator over the non-empty buckets of a headermap, in bucket order.
+///
+/// Each item is either the entry together with its bucket index or the
+/// error for a bucket which could not be parsed, so corrupt buckets do
+/// not stop the enumeration. The exception is a bucket table which is cut
+/// off: the first bucket past the end of the bytes is reported once as
+/// `BucketErrorKind::OffsetParseError` and enumeration stops, as none of
+/// the following buckets can be read either.
+pub struct Entries<'a> {
+    bytes: &'a [u8],
+    parse_result: DataHeaderParseResult,
+    bucket_index: BucketCountType,
+}
+
+impl<'a> Entries<'a> {
+    /// Returns the data header and whether the bytes are byte swapped.
+    pub fn header(&self) -> &DataHeaderParseResult {
+        &self.parse_result
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = std::result::Result<(BucketCountType, Entry<'a>), BucketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.bucket_index < self.parse_result.header.bucket_count {
+            let bucket_index = self.bucket_index;
+            self.bucket_index += 1;
+
+            let entry_result = bucket::parse_entry_at_bucket_index(
+                self.bytes,
+                &self.parse_result.header,
+                bucket_index,
+                self.parse_result.swap_bytes,
+            );
+
+            // If the entry is `None`, it means the hash bucket was empty,
+            // it's not an error condition.
+            match entry_result {
+                Ok(Some(entry)) => return Some(Ok((bucket_index, entry))),
+                Ok(None) => continue,
+                Err(bucket_error) => {
+                    if let BucketErrorKind::OffsetParseError = bucket_error.kind {
+                        self.bucket_index = self.parse_result.header.bucket_count;
+                    }
+                    return Some(Err(bucket_error));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining_bucket_count =
+            (self.parse_result.header.bucket_count - self.bucket_index) as usize;
+        (0, Some(remaining_bucket_count))
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Entries<'a> {}
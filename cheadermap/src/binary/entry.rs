@@ -1,14 +1,98 @@
 // Copyright (c) 2022 Milen Dzhumerov
 
-#[derive(Debug)]
+use std::path::PathBuf;
 
 /// A headermap entry consists of three strings: `key`, `prefix` and `suffix`.
 /// Conceptually, each entry represents a map entry from `key` -> `prefix` + `suffix`.
 ///
 /// They `key` is what appears in `#include` directives and gets mapped to an
 /// actual path (i.e., `prefix` + `suffix`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Entry<'a> {
     pub key: &'a str,
     pub prefix: &'a str,
     pub suffix: &'a str,
 }
+
+impl<'a> Entry<'a> {
+    /// Returns the path the key maps to. Like Clang, `prefix` and `suffix`
+    /// are concatenated as strings, so `prefix` is expected to end with a
+    /// path separator.
+    pub fn target_path(&self) -> PathBuf {
+        PathBuf::from(format!("{}{}", self.prefix, self.suffix))
+    }
+
+    pub fn to_owned_entry(&self) -> OwnedEntry {
+        OwnedEntry {
+            key: self.key.to_owned(),
+            prefix: self.prefix.to_owned(),
+            suffix: self.suffix.to_owned(),
+        }
+    }
+}
+
+/// Prints the entry in the format `key -> prefix + suffix`.
+impl<'a> std::fmt::Display for Entry<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}{}", self.key, self.prefix, self.suffix)
+    }
+}
+
+/// An `Entry` which owns its strings, so it's not tied to the lifetime
+/// of the headermap bytes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OwnedEntry {
+    pub key: String,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl OwnedEntry {
+    pub fn new<K, P, S>(key: K, prefix: P, suffix: S) -> OwnedEntry
+    where
+        K: Into<String>,
+        P: Into<String>,
+        S: Into<String>,
+    {
+        OwnedEntry {
+            key: key.into(),
+            prefix: prefix.into(),
+            suffix: suffix.into(),
+        }
+    }
+
+    /// Creates an entry which maps `key` to `target_path`, splitting the
+    /// path like Xcode does: `prefix` is everything up to and including
+    /// the last `/`, `suffix` is the file name.
+    pub fn from_target_path(key: &str, target_path: &str) -> OwnedEntry {
+        let split_index = target_path.rfind('/').map_or(0, |index| index + 1);
+        let (prefix, suffix) = target_path.split_at(split_index);
+        OwnedEntry::new(key, prefix, suffix)
+    }
+
+    pub fn as_entry(&self) -> Entry<'_> {
+        Entry {
+            key: &self.key,
+            prefix: &self.prefix,
+            suffix: &self.suffix,
+        }
+    }
+
+    /// See `Entry::target_path()`.
+    pub fn target_path(&self) -> PathBuf {
+        self.as_entry().target_path()
+    }
+}
+
+impl<'a> From<Entry<'a>> for OwnedEntry {
+    fn from(entry: Entry<'a>) -> OwnedEntry {
+        entry.to_owned_entry()
+    }
+}
+
+/// Prints the entry in the format `key -> prefix + suffix`.
+impl std::fmt::Display for OwnedEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_entry().fmt(f)
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    edit::EditError, expand::ExpandError, filter::FilterError, generate::GenerateError,
+    header::DataHeaderError, json::JsonParseError, merge::MergeError, params::ParamsError,
+    types::BucketCountType, writer::HeaderMapWriteError, xcode::TargetDescriptionError,
+};
+
+/// Errors returned when reading or writing headermaps.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid data header: {0}")]
+    Header(#[from] DataHeaderError),
+    #[error(transparent)]
+    Bucket(#[from] BucketError),
+    #[error(transparent)]
+    Write(#[from] HeaderMapWriteError),
+    #[error(transparent)]
+    Json(#[from] JsonParseError),
+    #[error(transparent)]
+    Params(#[from] ParamsError),
+    #[error(transparent)]
+    Merge(#[from] MergeError),
+    #[error(transparent)]
+    Generate(#[from] GenerateError),
+    #[error(transparent)]
+    TargetDescription(#[from] TargetDescriptionError),
+    #[error(transparent)]
+    Expand(#[from] ExpandError),
+    #[error(transparent)]
+    Filter(#[from] FilterError),
+    #[error(transparent)]
+    Edit(#[from] EditError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error in a particular bucket. `offset` is the absolute byte offset
+/// of the data which could not be parsed: the string offset field for
+/// `BucketErrorKind::OffsetParseError` or the start of the string for
+/// `BucketErrorKind::InvalidString`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid bucket at index `{bucket_index}`, offset `{offset}`: {kind}")]
+pub struct BucketError {
+    pub bucket_index: BucketCountType,
+    pub offset: usize,
+    pub kind: BucketErrorKind,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BucketErrorKind {
+    #[error("Could not parse string offset")]
+    OffsetParseError,
+    #[error("Invalid string: {0}")]
+    InvalidString(#[from] StringError),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StringError {
+    #[error("Overlaps the preamble section")]
+    OverlapsPreambleSection,
+    #[error("Out of bounds")]
+    OutOfBounds,
+    #[error("No NULL terminating byte found")]
+    NoNullTerminatingByteFound,
+    #[error("UTF-8 parsing error: {0}")]
+    InvalidUtf8(std::str::Utf8Error),
+}
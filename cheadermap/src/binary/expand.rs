@@ -0,0 +1,152 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::binary::{
+    entry::{Entry, OwnedEntry},
+    error::Result,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExpandError {
+    #[error("Undefined variable(s): {}", format_variable_names(.0))]
+    UndefinedVariables(Vec<String>),
+    #[error("Unterminated placeholder in `{0}`")]
+    UnterminatedPlaceholder(String),
+    #[error("Invalid variable definition, expected `NAME=VALUE`, found `{0}`")]
+    InvalidVariableDefinition(String),
+}
+
+fn format_variable_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses a `NAME=VALUE` variable definition.
+pub fn parse_variable_definition(definition: &str) -> Result<(String, String)> {
+    match definition.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), value.to_owned()))
+        }
+        _ => Err(ExpandError::InvalidVariableDefinition(definition.to_owned()).into()),
+    }
+}
+
+/// Parses a variable file with one `NAME=VALUE` definition per line.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn parse_variable_file(bytes: &[u8]) -> Result<HashMap<String, String>> {
+    let contents = String::from_utf8_lossy(bytes);
+    contents
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_variable_definition)
+        .collect()
+}
+
+/// Expands the `$(NAME)` and `${NAME}` placeholders in the targets
+/// (i.e., `prefix` and `suffix`) of `entries`. Keys are left unchanged.
+/// A `$` which does not start a placeholder is kept as is.
+///
+/// All undefined variables get reported in a single error.
+pub fn expand_entries(
+    entries: &[Entry],
+    variables: &HashMap<String, String>,
+) -> Result<Vec<OwnedEntry>> {
+    let mut undefined_variables = BTreeSet::new();
+    let mut expanded_entries = Vec::new();
+    for entry in entries {
+        let prefix = expand_placeholders(entry.prefix, variables, &mut undefined_variables)?;
+        let suffix = expand_placeholders(entry.suffix, variables, &mut undefined_variables)?;
+        expanded_entries.push(OwnedEntry::new(entry.key, prefix, suffix));
+    }
+
+    if !undefined_variables.is_empty() {
+        return Err(
+            ExpandError::UndefinedVariables(undefined_variables.into_iter().collect()).into(),
+        );
+    }
+
+    Ok(expanded_entries)
+}
+
+fn expand_placeholders(
+    string: &str,
+    variables: &HashMap<String, String>,
+    undefined_variables: &mut BTreeSet<String>,
+) -> std::result::Result<String, ExpandError> {
+    let mut expanded = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(dollar_index) = rest.find('$') {
+        expanded.push_str(&rest[..dollar_index]);
+        let placeholder = &rest[dollar_index..];
+
+        let closing_delimiter = match placeholder.as_bytes().get(1) {
+            Some(b'(') => ')',
+            Some(b'{') => '}',
+            _ => {
+                expanded.push('$');
+                rest = &placeholder[1..];
+                continue;
+            }
+        };
+        let name_length = placeholder[2..]
+            .find(closing_delimiter)
+            .ok_or_else(|| ExpandError::UnterminatedPlaceholder(string.to_owned()))?;
+        let name = &placeholder[2..2 + name_length];
+
+        match variables.get(name) {
+            Some(value) => expanded.push_str(value),
+            None => {
+                undefined_variables.insert(name.to_owned());
+            }
+        }
+        rest = &placeholder[2 + name_length + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_placeholders() {
+        let variables = HashMap::from([
+            ("SRCROOT".to_owned(), "/src".to_owned()),
+            ("BUILD_ROOT".to_owned(), "/build".to_owned()),
+        ]);
+        let mut undefined_variables = BTreeSet::new();
+
+        let expanded = expand_placeholders(
+            "$(SRCROOT)/a/${BUILD_ROOT}/$NOT_A_PLACEHOLDER$",
+            &variables,
+            &mut undefined_variables,
+        )
+        .unwrap();
+        assert_eq!(expanded, "/src/a//build/$NOT_A_PLACEHOLDER$");
+        assert!(undefined_variables.is_empty());
+
+        expand_placeholders("$(UNDEFINED)/a", &variables, &mut undefined_variables).unwrap();
+        assert_eq!(
+            undefined_variables.into_iter().collect::<Vec<_>>(),
+            vec!["UNDEFINED".to_owned()]
+        );
+
+        let mut undefined_variables = BTreeSet::new();
+        assert!(expand_placeholders("$(SRCROOT", &variables, &mut undefined_variables).is_err());
+    }
+
+    #[test]
+    fn test_parse_variable_file() {
+        let variables = parse_variable_file(b"# Comment\n\nSRCROOT=/src\nEMPTY=\n").unwrap();
+        assert_eq!(variables.get("SRCROOT").unwrap(), "/src");
+        assert_eq!(variables.get("EMPTY").unwrap(), "");
+        assert!(parse_variable_file(b"SRCROOT").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::HashSet;
+
+use crate::binary::{entry::Entry, error::Result};
+
+#[derive(thiserror::Error, Debug)]
+pub enum FilterError {
+    #[cfg(feature = "regex")]
+    #[error("Invalid regex `{0}`: {1}")]
+    InvalidRegex(String, regex::Error),
+    #[error("Invalid glob `{0}`")]
+    InvalidGlob(String),
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Glob(Vec<GlobToken>),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    fn is_match(&self, string: &str) -> bool {
+        match self {
+            Pattern::Glob(tokens) => glob_matches(tokens, string),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(string),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum GlobToken {
+    Character(char),
+    /// `?`
+    AnyCharacter,
+    /// `*`
+    AnyCharactersInComponent,
+    /// `**`
+    AnyCharacters,
+    /// `[...]` or `[!...]`, single characters are stored as `(c, c)`.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// Selects entries by key or target. Patterns of the same kind are
+/// alternatives, i.e., an entry has to match any of the key patterns
+/// (if there are any) and any of the target patterns (if there are any)
+/// and be in the key list (if there is one).
+///
+/// Globs have to match the whole string, `*` and `?` do not match `/`
+/// while `**` matches anything. Regexes match anywhere, unless anchored,
+/// and require the `regex` feature. The key list is matched
+/// case-insensitively, like Clang matches keys.
+#[derive(Debug, Default)]
+pub struct EntryFilter {
+    key_patterns: Vec<Pattern>,
+    target_patterns: Vec<Pattern>,
+    lowercase_keys: Option<HashSet<String>>,
+    invert: bool,
+}
+
+impl EntryFilter {
+    pub fn new() -> EntryFilter {
+        EntryFilter::default()
+    }
+
+    pub fn add_key_glob(&mut self, glob: &str) -> Result<()> {
+        self.key_patterns.push(compile_glob(glob)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn add_key_regex(&mut self, pattern: &str) -> Result<()> {
+        self.key_patterns.push(compile_regex(pattern)?);
+        Ok(())
+    }
+
+    /// Matches the target path, i.e., `prefix` + `suffix`.
+    pub fn add_target_glob(&mut self, glob: &str) -> Result<()> {
+        self.target_patterns.push(compile_glob(glob)?);
+        Ok(())
+    }
+
+    /// Matches the target path, i.e., `prefix` + `suffix`.
+    #[cfg(feature = "regex")]
+    pub fn add_target_regex(&mut self, pattern: &str) -> Result<()> {
+        self.target_patterns.push(compile_regex(pattern)?);
+        Ok(())
+    }
+
+    pub fn add_keys<I, S>(&mut self, keys: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.lowercase_keys.get_or_insert_with(HashSet::new).extend(
+            keys.into_iter()
+                .map(|key| key.as_ref().to_ascii_lowercase()),
+        );
+    }
+
+    /// If `true`, selects the entries which do not match.
+    pub fn set_invert(&mut self, invert: bool) {
+        self.invert = invert;
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let key_matches = self.key_patterns.is_empty()
+            || self
+                .key_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(entry.key));
+        let target_matches = self.target_patterns.is_empty() || {
+            let target_path = format!("{}{}", entry.prefix, entry.suffix);
+            self.target_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&target_path))
+        };
+        let key_listed = match &self.lowercase_keys {
+            Some(keys) => keys.contains(&entry.key.to_ascii_lowercase()),
+            None => true,
+        };
+
+        (key_matches && target_matches && key_listed) != self.invert
+    }
+
+    /// Returns the selected entries, keeping their order.
+    pub fn filter<'a>(&self, entries: &[Entry<'a>]) -> Vec<Entry<'a>> {
+        entries
+            .iter()
+            .filter(|entry| self.matches(entry))
+            .copied()
+            .collect()
+    }
+}
+
+/// Parses a key list with one key per line. Empty lines and lines
+/// starting with `#` are ignored.
+pub fn parse_key_list(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(feature = "regex")]
+fn compile_regex(pattern: &str) -> Result<Pattern> {
+    regex::Regex::new(pattern)
+        .map(Pattern::Regex)
+        .map_err(|err| FilterError::InvalidRegex(pattern.to_owned(), err).into())
+}
+
+fn compile_glob(glob: &str) -> Result<Pattern> {
+    let invalid_glob = || FilterError::InvalidGlob(glob.to_owned());
+    let mut tokens = Vec::new();
+    let mut characters = glob.chars().peekable();
+    while let Some(character) = characters.next() {
+        let token = match character {
+            '*' if characters.next_if_eq(&'*').is_some() => GlobToken::AnyCharacters,
+            '*' => GlobToken::AnyCharactersInComponent,
+            '?' => GlobToken::AnyCharacter,
+            '[' => {
+                let negated = characters.next_if_eq(&'!').is_some();
+                let mut ranges = Vec::new();
+                loop {
+                    let start = match characters.next() {
+                        Some(']') if !ranges.is_empty() => break,
+                        Some(']') | None => return Err(invalid_glob().into()),
+                        Some(start) => start,
+                    };
+                    let mut lookahead = characters.clone();
+                    let end = match (lookahead.next(), lookahead.next()) {
+                        (Some('-'), Some(end)) if end != ']' => {
+                            characters.next();
+                            characters.next();
+                            end
+                        }
+                        _ => start,
+                    };
+                    if end < start {
+                        return Err(invalid_glob().into());
+                    }
+                    ranges.push((start, end));
+                }
+                GlobToken::Class { negated, ranges }
+            }
+            character => GlobToken::Character(character),
+        };
+        tokens.push(token);
+    }
+
+    Ok(Pattern::Glob(tokens))
+}
+
+/// Returns `true` if the glob `tokens` match all of `string`.
+fn glob_matches(tokens: &[GlobToken], string: &str) -> bool {
+    let characters: Vec<char> = string.chars().collect();
+
+    // `matches[index]` is `true` if the remaining tokens match
+    // `characters[index..]`, computed from the last token backwards.
+    let mut matches = vec![false; characters.len() + 1];
+    matches[characters.len()] = true;
+    for token in tokens.iter().rev() {
+        let mut token_matches = vec![false; characters.len() + 1];
+        for index in (0..=characters.len()).rev() {
+            let character = characters.get(index).copied();
+            token_matches[index] = match token {
+                GlobToken::AnyCharacters => {
+                    matches[index] || (character.is_some() && token_matches[index + 1])
+                }
+                GlobToken::AnyCharactersInComponent => {
+                    matches[index]
+                        || (matches!(character, Some(character) if character != '/')
+                            && token_matches[index + 1])
+                }
+                _ => {
+                    matches!(character, Some(character) if glob_token_matches(token, character))
+                        && matches[index + 1]
+                }
+            };
+        }
+        matches = token_matches;
+    }
+
+    matches[0]
+}
+
+/// Returns `true` if a single character `token` matches `character`.
+fn glob_token_matches(token: &GlobToken, character: char) -> bool {
+    match token {
+        GlobToken::Character(expected) => character == *expected,
+        GlobToken::AnyCharacter => character != '/',
+        GlobToken::Class { negated, ranges } => {
+            ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&character))
+                != *negated
+        }
+        GlobToken::AnyCharactersInComponent | GlobToken::AnyCharacters => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_glob() {
+        let glob = compile_glob("Foo/*.h").unwrap();
+        assert!(glob.is_match("Foo/Bar.h"));
+        assert!(!glob.is_match("Foo/Bar/Baz.h"));
+        assert!(!glob.is_match("Foo/Bar.hpp"));
+
+        let glob = compile_glob("**/Private/?.[hm]").unwrap();
+        assert!(glob.is_match("/a/b/Private/A.h"));
+        assert!(glob.is_match("/a/Private/B.m"));
+        assert!(!glob.is_match("/a/Private/AB.h"));
+
+        let glob = compile_glob("[!a]+.h").unwrap();
+        assert!(glob.is_match("b+.h"));
+        assert!(!glob.is_match("a+.h"));
+
+        let glob = compile_glob("*[0-9a-].h").unwrap();
+        assert!(glob.is_match("Foo7.h"));
+        assert!(glob.is_match("Fooa.h"));
+        assert!(glob.is_match("Foo-.h"));
+        assert!(!glob.is_match("FooB.h"));
+
+        let glob = compile_glob("**").unwrap();
+        assert!(glob.is_match(""));
+        assert!(glob.is_match("/a/b"));
+        let glob = compile_glob("a*b*c").unwrap();
+        assert!(glob.is_match("abc"));
+        assert!(glob.is_match("aXbYbZc"));
+        assert!(!glob.is_match("aXbY/c"));
+        assert!(!glob.is_match("aXbYc/"));
+
+        assert!(compile_glob("[a").is_err());
+        assert!(compile_glob("[]").is_err());
+        assert!(compile_glob("[z-a]").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::binary::{entry::OwnedEntry, error::Result};
+
+/// The header file extensions used if none are configured.
+pub const DEFAULT_HEADER_EXTENSIONS: [&str; 4] = ["h", "hpp", "hh", "inc"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum GenerateError {
+    #[error("Path is not valid UTF-8, found `{0}`")]
+    NonUtf8Path(PathBuf),
+}
+
+/// Options for `generate_headermap_entries`.
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// File extensions (without the leading `.`) of the headers to map,
+    /// matched case-insensitively.
+    pub extensions: Vec<String>,
+    /// Headers get mapped by `<namespace>/<file name>` as well as by their
+    /// file name. If `None`, the namespace is the name of the scanned
+    /// directory the header was found in.
+    pub namespace: Option<String>,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> GenerateOptions {
+        GenerateOptions {
+            extensions: DEFAULT_HEADER_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            namespace: None,
+        }
+    }
+}
+
+/// The entries generated from header paths.
+#[derive(Debug, Default)]
+pub struct GeneratedEntries {
+    pub entries: Vec<OwnedEntry>,
+    /// Entries whose key matches the key of an earlier entry when
+    /// compared case-insensitively, so they got dropped.
+    pub duplicate_entries: Vec<OwnedEntry>,
+    lowercase_keys: HashSet<String>,
+}
+
+impl GeneratedEntries {
+    /// Maps the header at `target_path` by its file name and, if given,
+    /// by `<namespace>/<file name>`. Like Xcode, the target gets split
+    /// into the directory as `prefix` and the file name as `suffix`.
+    pub(crate) fn add_header(&mut self, target_path: &str, namespace: Option<&str>) {
+        let target = OwnedEntry::from_target_path("", target_path);
+        let mut keys = vec![target.suffix.clone()];
+        if let Some(namespace) = namespace {
+            keys.push(format!("{}/{}", namespace, target.suffix));
+        }
+
+        for key in keys {
+            self.add_entry(OwnedEntry::new(key, &target.prefix, &target.suffix));
+        }
+    }
+
+    /// Adds `entry` unless its key collides with an earlier entry.
+    pub(crate) fn add_entry(&mut self, entry: OwnedEntry) {
+        if self.lowercase_keys.insert(entry.key.to_ascii_lowercase()) {
+            self.entries.push(entry);
+        } else {
+            self.duplicate_entries.push(entry);
+        }
+    }
+}
+
+/// Walks `directories` recursively and maps every header found, see
+/// `GenerateOptions`. Target paths are absolute. Directories are walked
+/// in the order given, their contents in path order. If keys collide
+/// (case-insensitively, like Clang), the first header wins.
+pub fn generate_headermap_entries<P>(
+    directories: &[P],
+    options: &GenerateOptions,
+) -> Result<GeneratedEntries>
+where
+    P: AsRef<Path>,
+{
+    let mut generated_entries = GeneratedEntries::default();
+    for directory in directories {
+        let directory = std::fs::canonicalize(directory.as_ref())?;
+        let namespace = match &options.namespace {
+            Some(namespace) => Some(namespace.as_str()),
+            None => directory
+                .file_name()
+                .map(|file_name| {
+                    file_name
+                        .to_str()
+                        .ok_or_else(|| GenerateError::NonUtf8Path(directory.clone()))
+                })
+                .transpose()?,
+        };
+
+        let mut header_paths = Vec::new();
+        collect_header_paths(&directory, &options.extensions, &mut header_paths)?;
+        for header_path in header_paths {
+            let target_path = header_path
+                .to_str()
+                .ok_or_else(|| GenerateError::NonUtf8Path(header_path.clone()))?;
+            generated_entries.add_header(target_path, namespace);
+        }
+    }
+
+    Ok(generated_entries)
+}
+
+fn collect_header_paths(
+    directory: &Path,
+    extensions: &[String],
+    header_paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut paths = std::fs::read_dir(directory)?
+        .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    for path in paths {
+        // Symlinked directories are not followed to avoid cycles.
+        let file_type = std::fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_dir() {
+            collect_header_paths(&path, extensions, header_paths)?;
+        } else if path.is_file() && has_header_extension(&path, extensions) {
+            header_paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn has_header_extension(path: &Path, extensions: &[String]) -> bool {
+    let extension = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extension,
+        None => return false,
+    };
+    extensions.iter().any(|header_extension| {
+        header_extension
+            .trim_start_matches('.')
+            .eq_ignore_ascii_case(extension)
+    })
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::types::BucketCountType;
+
+/// Computes the hash of a headermap key, mirroring `HashHMapKey()` in
+/// Clang's HeaderMap.cpp: each byte is ASCII lowercased, multiplied by 13
+/// and added to the result (with wrapping arithmetic).
+///
+/// Keys which only differ in ASCII case have the same hash, as Clang
+/// compares keys case-insensitively. Non-ASCII bytes are not lowercased.
+/// Clang iterates over the key as `char`, which is signed on Apple
+/// targets, so bytes >= 0x80 are sign-extended before the multiplication.
+pub fn hash_key(key: &str) -> u32 {
+    key.bytes().fold(0u32, |result, byte| {
+        result.wrapping_add((byte.to_ascii_lowercase() as i8 as i32 as u32).wrapping_mul(13))
+    })
+}
+
+/// Returns the bucket Clang starts probing at when looking up `key` in a
+/// bucket table of `bucket_count` buckets. Like in Clang, `bucket_count`
+/// has to be a power of two.
+pub fn bucket_index_for_key(key: &str, bucket_count: BucketCountType) -> BucketCountType {
+    debug_assert!(bucket_count.is_power_of_two());
+    hash_key(key) & (bucket_count - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys of the SDWebImage fixture written by Xcode with the hashes of
+    /// Clang's `HashHMapKey()` and the buckets Xcode stored them in. The
+    /// bucket before each of them is empty, so none got displaced.
+    const SDWEBIMAGE_KEYS: [(&str, u32, BucketCountType); 6] = [
+        ("SDWebImage/SDWeakProxy.h", 32006, 6),
+        ("SDWebImage/SDWebImageIndicator.h", 42250, 10),
+        ("SDWebImage/SDImageCacheDefine.h", 40222, 30),
+        ("SDWebImage/SDWebImage.h", 29809, 113),
+        ("SDWebImage/SDWebImageManager.h", 39312, 144),
+        ("SDWebImage/SDImageCache.h", 32175, 175),
+    ];
+
+    #[test]
+    fn test_hash_key() {
+        assert_eq!(hash_key(""), 0);
+        for (key, hash, _) in SDWEBIMAGE_KEYS {
+            assert_eq!(hash_key(key), hash, "{}", key);
+            assert_eq!(hash_key(&key.to_ascii_uppercase()), hash, "{}", key);
+        }
+
+        // Clang's `HashHMapKey()` built with a signed `char`, as on Apple
+        // targets, sign-extends bytes >= 0x80.
+        assert_eq!(hash_key("é"), 4294965372);
+        assert_eq!(hash_key("SDWebImage/Ünïcode.h"), 19019);
+        assert_ne!(hash_key("É"), hash_key("é"));
+    }
+
+    #[test]
+    fn test_hash_key_wraps() {
+        let key = "\u{7f}".repeat(5_000_000);
+        assert_eq!(
+            hash_key(&key),
+            0x7Fu32.wrapping_mul(13).wrapping_mul(5_000_000)
+        );
+    }
+
+    #[test]
+    fn test_bucket_index_for_key() {
+        for (key, _, bucket_index) in SDWEBIMAGE_KEYS {
+            assert_eq!(bucket_index_for_key(key, 256), bucket_index, "{}", key);
+            assert_eq!(bucket_index_for_key(key, 1), 0);
+        }
+        assert_eq!(bucket_index_for_key("SDWebImage/Ünïcode.h", 256), 75);
+    }
+}
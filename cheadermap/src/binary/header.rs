@@ -2,8 +2,14 @@
 
 use crate::binary::{types::*, utility::Packable};
 
-const MAGIC_NATIVE_ENDIAN: MagicType = 0x68_6D_61_70; // 'hmap' (in big endian)
-const MAGIC_NON_NATIVE_ENDIAN: MagicType = MAGIC_NATIVE_ENDIAN.swap_bytes();
+pub const MAGIC_NATIVE_ENDIAN: MagicType = 0x68_6D_61_70; // 'hmap' (in big endian)
+pub(crate) const MAGIC_NON_NATIVE_ENDIAN: MagicType = MAGIC_NATIVE_ENDIAN.swap_bytes();
+
+// Byte offsets of the data header fields.
+pub(crate) const STRING_SECTION_OFFSET_FIELD_OFFSET: usize = 8;
+pub(crate) const STRING_COUNT_FIELD_OFFSET: usize = 12;
+pub(crate) const BUCKET_COUNT_FIELD_OFFSET: usize = 16;
+pub(crate) const MAX_VALUE_LENGTH_FIELD_OFFSET: usize = 20;
 
 /// Represents the headermap file header.
 ///
@@ -12,7 +18,7 @@ const MAGIC_NON_NATIVE_ENDIAN: MagicType = MAGIC_NATIVE_ENDIAN.swap_bytes();
 ///  - HeaderMap.h
 ///  - HeaderMap.cpp
 ///  - HeaderMapTypes.h
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DataHeader {
     pub magic: MagicType,
     pub version: VersionType,
@@ -35,8 +41,27 @@ impl Packable for DataHeader {
     }
 }
 
-use crate::binary::{byte_decoder::ByteDecoder, utility::ByteSwappable};
-use thiserror;
+use crate::binary::{
+    bucket::Bucket, byte_decoder::ByteDecoder, byte_encoder::ByteEncoder, utility::ByteSwappable,
+};
+
+impl DataHeader {
+    /// Encodes the header in native byte order.
+    pub fn encode(&self, encoder: &mut ByteEncoder) {
+        encoder.append(self.magic);
+        encoder.append(self.version);
+        encoder.append(self.reserved);
+        encoder.append(self.string_section_offset);
+        encoder.append(self.string_count);
+        encoder.append(self.bucket_count);
+        encoder.append(self.max_value_length);
+    }
+
+    /// Returns the absolute byte offset of the end of the bucket table.
+    pub(crate) fn bucket_table_end(&self) -> usize {
+        DataHeader::packed_size() + (self.bucket_count as usize) * Bucket::packed_size()
+    }
+}
 
 #[derive(thiserror::Error, Debug)]
 pub enum DataHeaderError {
@@ -64,9 +89,11 @@ pub enum DataHeaderError {
     MissingMaxValueLengthValue,
     #[error("String section is out of bounds, found `{0}`")]
     StringSectionOffsetOutOfBounds(StringSectionOffsetType),
+    #[error("Bucket table is out of bounds, found `{0}` buckets")]
+    BucketTableOutOfBounds(BucketCountType),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DataHeaderParseResult {
     pub header: DataHeader,
     /// If the endianness of the binary data is different,
@@ -77,14 +104,18 @@ pub struct DataHeaderParseResult {
     pub swap_bytes: bool,
 }
 
-pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
+/// Parses and validates the data header at the start of `bytes`.
+///
+/// The bucket count is a power of two, in particular not zero, so
+/// `bucket_count - 1` is the mask for bucket indices.
+pub fn parse_header(bytes: &[u8]) -> Result<DataHeaderParseResult, DataHeaderError> {
     let mut decoder = ByteDecoder { bytes, offset: 0 };
     let magic = decoder
         .advance::<MagicType>()
         .ok_or(DataHeaderError::MissingMagic)?;
 
     if magic != MAGIC_NATIVE_ENDIAN && magic != MAGIC_NON_NATIVE_ENDIAN {
-        anyhow::bail!(DataHeaderError::UnknownMagic(magic));
+        return Err(DataHeaderError::UnknownMagic(magic));
     }
 
     let swap_bytes = magic == MAGIC_NON_NATIVE_ENDIAN;
@@ -94,7 +125,7 @@ pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
         .ok_or(DataHeaderError::MissingVersion)?
         .swap_bytes_if(swap_bytes);
     if version != VERSION_1 {
-        anyhow::bail!(DataHeaderError::UnsupportedVersion(version));
+        return Err(DataHeaderError::UnsupportedVersion(version));
     }
 
     let reserved = decoder
@@ -102,7 +133,7 @@ pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
         .ok_or(DataHeaderError::MissingReservedValue)?
         .swap_bytes_if(swap_bytes);
     if reserved != RESERVED {
-        anyhow::bail!(DataHeaderError::UnsupportedReserved(reserved));
+        return Err(DataHeaderError::UnsupportedReserved(reserved));
     }
 
     let string_section_offset = decoder
@@ -110,8 +141,8 @@ pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
         .ok_or(DataHeaderError::MissingStringSectionOffsetValue)?
         .swap_bytes_if(swap_bytes);
     if bytes.len() < (string_section_offset as usize) {
-        anyhow::bail!(DataHeaderError::StringSectionOffsetOutOfBounds(
-            string_section_offset
+        return Err(DataHeaderError::StringSectionOffsetOutOfBounds(
+            string_section_offset,
         ));
     }
 
@@ -125,7 +156,7 @@ pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
         .ok_or(DataHeaderError::MissingBucketCountValue)?
         .swap_bytes_if(swap_bytes);
     if !bucket_count.is_power_of_two() {
-        anyhow::bail!(DataHeaderError::BucketCountNotPowerOfTwo(bucket_count));
+        return Err(DataHeaderError::BucketCountNotPowerOfTwo(bucket_count));
     }
 
     let max_value_length = decoder
@@ -145,3 +176,16 @@ pub fn parse_header(bytes: &[u8]) -> anyhow::Result<DataHeaderParseResult> {
 
     Ok(DataHeaderParseResult { header, swap_bytes })
 }
+
+/// Checks that the bucket table fits into `bytes`. Clang rejects headermaps
+/// whose bucket table does not fit, but `parse_header()` does not check it,
+/// so that truncated headermaps can still be salvaged.
+pub(crate) fn check_bucket_table_bounds(
+    header: &DataHeader,
+    bytes: &[u8],
+) -> Result<(), DataHeaderError> {
+    if header.bucket_table_end() > bytes.len() {
+        return Err(DataHeaderError::BucketTableOutOfBounds(header.bucket_count));
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::borrow::Cow;
+
+use crate::binary::{
+    bucket::{self, BucketState},
+    entries::entries,
+    entry::Entry,
+    error::{BucketError, Result},
+    hash::hash_key,
+    header::{self, DataHeaderParseResult},
+    trace::{LookupTrace, ProbedBucket, ProbedBucketState},
+    types::BucketCountType,
+};
+
+/// A validated headermap which either owns or borrows its binary
+/// representation. Entries are decoded on demand.
+///
+/// `from_bytes()` validates all buckets upfront, so accessing entries
+/// cannot fail. A headermap created by `from_bytes_lenient()` can contain
+/// corrupt buckets, which get skipped like Clang does.
+pub struct HeaderMap<'a> {
+    bytes: Cow<'a, [u8]>,
+    parse_result: DataHeaderParseResult,
+    entry_count: usize,
+    bucket_errors: Vec<BucketError>,
+}
+
+impl HeaderMap<'static> {
+    /// Reads and validates the headermap at `path`.
+    pub fn open<P>(path: P) -> Result<HeaderMap<'static>>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file_bytes = std::fs::read(path.as_ref())?;
+        HeaderMap::from_bytes(file_bytes)
+    }
+}
+
+impl<'a> HeaderMap<'a> {
+    /// Validates the headermap in `bytes`, which can be either owned
+    /// (`Vec<u8>`) or borrowed (`&[u8]`). Returns an error if the header
+    /// or any of the buckets are invalid.
+    pub fn from_bytes<B>(bytes: B) -> Result<HeaderMap<'a>>
+    where
+        B: Into<Cow<'a, [u8]>>,
+    {
+        let bytes = bytes.into();
+        let parse_result = header::parse_header(&bytes)?;
+
+        let mut entry_count = 0;
+        for bucket_index in 0..parse_result.header.bucket_count {
+            let maybe_entry = bucket::parse_entry_at_bucket_index(
+                &bytes,
+                &parse_result.header,
+                bucket_index,
+                parse_result.swap_bytes,
+            )?;
+            if maybe_entry.is_some() {
+                entry_count += 1;
+            }
+        }
+
+        Ok(HeaderMap {
+            bytes,
+            parse_result,
+            entry_count,
+            bucket_errors: Vec::new(),
+        })
+    }
+
+    /// Like `from_bytes()`, but only validates what Clang validates when
+    /// loading a headermap: the header and that the bucket table fits into
+    /// `bytes`. Corrupt buckets are not counted by `len()` and skipped by
+    /// `iter()`, see `lookup()` for how they affect lookups.
+    pub fn from_bytes_lenient<B>(bytes: B) -> Result<HeaderMap<'a>>
+    where
+        B: Into<Cow<'a, [u8]>>,
+    {
+        let bytes = bytes.into();
+        let parse_result = header::parse_header(&bytes)?;
+
+        header::check_bucket_table_bounds(&parse_result.header, &bytes)?;
+
+        let mut entry_count = 0;
+        let mut bucket_errors = Vec::new();
+        for maybe_entry in entries(&bytes)? {
+            match maybe_entry {
+                Ok(_) => entry_count += 1,
+                Err(bucket_error) => bucket_errors.push(bucket_error),
+            }
+        }
+
+        Ok(HeaderMap {
+            bytes,
+            parse_result,
+            entry_count,
+            bucket_errors,
+        })
+    }
+
+    /// Returns the data header and whether the bytes are byte swapped.
+    pub fn header(&self) -> &DataHeaderParseResult {
+        &self.parse_result
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// Returns the errors of the corrupt buckets in bucket order. Always
+    /// empty unless created by `from_bytes_lenient()`.
+    pub fn bucket_errors(&self) -> &[BucketError] {
+        &self.bucket_errors
+    }
+
+    /// Returns an iterator over the entries in bucket order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            headermap: self,
+            bucket_index: 0,
+        }
+    }
+
+    /// Returns the entry whose key is exactly `key`. Unlike `lookup()`,
+    /// keys which differ only in case do not match.
+    pub fn get(&self, key: &str) -> Option<Entry<'_>> {
+        self.probe(key, |entry_key| entry_key == key)
+    }
+
+    /// Looks up the entry for `key` the same way Clang does in
+    /// `HeaderMapImpl::lookupFilename()`: starting at the bucket for the
+    /// hash of `key`, buckets are probed linearly until an entry with a
+    /// case-insensitively matching key or an empty bucket (a bucket whose
+    /// key offset is reserved) is found.
+    ///
+    /// Corrupt buckets, which only exist in headermaps created by
+    /// `from_bytes_lenient()`, are skipped if their key cannot be read.
+    /// If their key matches but the prefix or suffix cannot be read, Clang
+    /// resolves the key to an empty path, so the lookup misses.
+    ///
+    /// Unlike Clang, probing stops after visiting every bucket once, so
+    /// a headermap without empty buckets cannot cause an infinite loop.
+    /// Also, keys which are not valid UTF-8 or not NULL terminated count as
+    /// unreadable, while Clang compares their bytes.
+    pub fn lookup(&self, key: &str) -> Option<Entry<'_>> {
+        self.probe(key, |entry_key| entry_key.eq_ignore_ascii_case(key))
+    }
+
+    /// Looks up `key` like `lookup()`, but records every probed bucket
+    /// to explain why the lookup hits or misses.
+    pub fn trace_lookup(&self, key: &str) -> LookupTrace<'_> {
+        let bucket_count = self.parse_result.header.bucket_count;
+        // `parse_header()` rejects a bucket count of zero.
+        let bucket_mask = bucket_count - 1;
+        let hash = hash_key(key);
+        let start_bucket_index = hash & bucket_mask;
+
+        let mut probes = Vec::new();
+        for probe_count in 0..bucket_count {
+            let bucket_index = start_bucket_index.wrapping_add(probe_count) & bucket_mask;
+            let state = match self.bucket_state(bucket_index) {
+                BucketState::Occupied(entry) if entry.key.eq_ignore_ascii_case(key) => {
+                    ProbedBucketState::Match(entry)
+                }
+                BucketState::Occupied(entry) => ProbedBucketState::Mismatch {
+                    entry,
+                    home_bucket_index: hash_key(entry.key) & bucket_mask,
+                },
+                BucketState::Empty => ProbedBucketState::Empty,
+                BucketState::Corrupt {
+                    key: bucket_key,
+                    error,
+                } => ProbedBucketState::Corrupt {
+                    key: bucket_key,
+                    key_matches: matches!(bucket_key, Some(bucket_key) if bucket_key.eq_ignore_ascii_case(key)),
+                    error,
+                },
+            };
+            let is_last_probe = match &state {
+                ProbedBucketState::Mismatch { .. } => false,
+                ProbedBucketState::Corrupt { key_matches, .. } => *key_matches,
+                _ => true,
+            };
+            probes.push(ProbedBucket {
+                bucket_index,
+                state,
+            });
+            if is_last_probe {
+                break;
+            }
+        }
+
+        LookupTrace {
+            key: key.to_owned(),
+            hash,
+            bucket_count,
+            start_bucket_index,
+            probes,
+        }
+    }
+
+    fn probe<F>(&self, key: &str, matches: F) -> Option<Entry<'_>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let bucket_count = self.parse_result.header.bucket_count;
+        // `parse_header()` rejects a bucket count of zero.
+        let bucket_mask = bucket_count - 1;
+        let start_bucket_index = hash_key(key) & bucket_mask;
+
+        for probe_count in 0..bucket_count {
+            let bucket_index = start_bucket_index.wrapping_add(probe_count) & bucket_mask;
+            match self.bucket_state(bucket_index) {
+                BucketState::Occupied(entry) if matches(entry.key) => return Some(entry),
+                BucketState::Occupied(_) => continue,
+                BucketState::Empty => return None,
+                BucketState::Corrupt { key: Some(key), .. } if matches(key) => return None,
+                BucketState::Corrupt { .. } => continue,
+            }
+        }
+
+        None
+    }
+
+    /// Returns the entry in a bucket or `None` if the bucket is empty or corrupt.
+    fn entry_at_bucket_index(&self, bucket_index: BucketCountType) -> Option<Entry<'_>> {
+        bucket::parse_entry_at_bucket_index(
+            &self.bytes,
+            &self.parse_result.header,
+            bucket_index,
+            self.parse_result.swap_bytes,
+        )
+        .ok()
+        .flatten()
+    }
+
+    fn bucket_state(&self, bucket_index: BucketCountType) -> BucketState<'_> {
+        bucket::parse_bucket_state_at_index(
+            &self.bytes,
+            &self.parse_result.header,
+            bucket_index,
+            self.parse_result.swap_bytes,
+        )
+    }
+}
+
+/// Iterator over the entries of a `HeaderMap`, see `HeaderMap::iter()`.
+pub struct Iter<'h> {
+    headermap: &'h HeaderMap<'h>,
+    bucket_index: BucketCountType,
+}
+
+impl<'h> Iterator for Iter<'h> {
+    type Item = Entry<'h>;
+
+    fn next(&mut self) -> Option<Entry<'h>> {
+        while self.bucket_index < self.headermap.parse_result.header.bucket_count {
+            let maybe_entry = self.headermap.entry_at_bucket_index(self.bucket_index);
+            self.bucket_index += 1;
+            if maybe_entry.is_some() {
+                return maybe_entry;
+            }
+        }
+
+        None
+    }
+}
+
+impl<'h> IntoIterator for &'h HeaderMap<'h> {
+    type Item = Entry<'h>;
+    type IntoIter = Iter<'h>;
+
+    fn into_iter(self) -> Iter<'h> {
+        self.iter()
+    }
+}
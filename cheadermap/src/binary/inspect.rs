@@ -0,0 +1,377 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::ops::Range;
+
+use crate::binary::{
+    bucket::{self, Bucket},
+    byte_decoder::ByteDecoder,
+    error::{BucketError, Result},
+    header::{DataHeader, DataHeaderError, MAGIC_NATIVE_ENDIAN, MAGIC_NON_NATIVE_ENDIAN},
+    types::*,
+    utility::{ByteSwappable, DecodablePrimitive, Packable},
+};
+
+/// Unused string section ranges are previewed up to this many bytes.
+const MAX_PREVIEW_LENGTH: usize = 64;
+
+/// A data header field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderFieldLayout {
+    pub name: &'static str,
+    /// Absolute byte offset of the field.
+    pub offset: usize,
+    pub size: usize,
+    /// The bytes of the field interpreted in native byte order.
+    pub raw_value: u32,
+    /// The value of the field, i.e., `raw_value` byte swapped if the
+    /// headermap is not in native byte order.
+    pub value: u32,
+}
+
+/// A string offset field of a bucket.
+#[derive(Debug)]
+pub struct StringOffsetLayout<'a> {
+    /// Absolute byte offset of the field.
+    pub field_offset: usize,
+    /// The bytes of the field interpreted in native byte order.
+    pub raw_value: StringSectionOffsetType,
+    /// The offset relative to the string section.
+    pub value: StringSectionOffsetType,
+    /// The string the offset points to, `None` if the bucket is empty.
+    /// Like in Clang, a reserved prefix or suffix offset points to the
+    /// string at the start of the string section.
+    pub string: Option<std::result::Result<&'a str, BucketError>>,
+}
+
+impl<'a> StringOffsetLayout<'a> {
+    /// Returns `true` if the offset is `STRING_SECTION_OFFSET_RESERVED`.
+    /// Only a reserved key offset marks a bucket as empty.
+    pub fn is_reserved(&self) -> bool {
+        self.value == STRING_SECTION_OFFSET_RESERVED
+    }
+}
+
+#[derive(Debug)]
+pub struct BucketLayout<'a> {
+    pub index: BucketCountType,
+    /// Absolute byte offset of the bucket.
+    pub offset: usize,
+    pub key: StringOffsetLayout<'a>,
+    pub prefix: StringOffsetLayout<'a>,
+    pub suffix: StringOffsetLayout<'a>,
+}
+
+impl<'a> BucketLayout<'a> {
+    /// Returns `true` if the key offset is reserved. Like in Clang, the
+    /// prefix and suffix offsets of an empty bucket are ignored.
+    pub fn is_empty(&self) -> bool {
+        self.key.is_reserved()
+    }
+
+    pub fn string_offsets(&self) -> [&StringOffsetLayout<'a>; 3] {
+        [&self.key, &self.prefix, &self.suffix]
+    }
+}
+
+/// The byte layout of a headermap, as it's stored in the file.
+#[derive(Debug)]
+pub struct HeaderMapLayout<'a> {
+    pub swap_bytes: bool,
+    pub header_fields: Vec<HeaderFieldLayout>,
+    /// The buckets which fit into the file, so there might be fewer
+    /// than the bucket count.
+    pub buckets: Vec<BucketLayout<'a>>,
+    /// Absolute byte ranges of the string section which are not part of
+    /// any string referenced by a bucket. The reserved NULL byte at the
+    /// start of the string section is considered used.
+    pub unused_string_ranges: Vec<Range<usize>>,
+}
+
+impl<'a> HeaderMapLayout<'a> {
+    /// Returns the header field with the given name.
+    pub fn header_field(&self, name: &str) -> Option<&HeaderFieldLayout> {
+        self.header_fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Decodes the byte layout of a headermap. Unlike `parse_header()`, header
+/// values which Clang rejects (e.g., a bucket count which is not a power
+/// of two) are accepted, only an unknown magic or a truncated header
+/// result in an error.
+pub fn inspect_headermap(bytes: &[u8]) -> Result<HeaderMapLayout<'_>> {
+    let magic = MagicType::decode_from_bytes(bytes).ok_or(DataHeaderError::MissingMagic)?;
+    let swap_bytes = match magic {
+        MAGIC_NATIVE_ENDIAN => false,
+        MAGIC_NON_NATIVE_ENDIAN => true,
+        magic => return Err(DataHeaderError::UnknownMagic(magic).into()),
+    };
+
+    let mut decoder = ByteDecoder { bytes, offset: 0 };
+    let magic_field = decode_header_field::<MagicType>(
+        &mut decoder,
+        "magic",
+        swap_bytes,
+        DataHeaderError::MissingMagic,
+    )?;
+    let version_field = decode_header_field::<VersionType>(
+        &mut decoder,
+        "version",
+        swap_bytes,
+        DataHeaderError::MissingVersion,
+    )?;
+    let reserved_field = decode_header_field::<ReservedType>(
+        &mut decoder,
+        "reserved",
+        swap_bytes,
+        DataHeaderError::MissingReservedValue,
+    )?;
+    let string_section_offset_field = decode_header_field::<StringSectionOffsetType>(
+        &mut decoder,
+        "string_section_offset",
+        swap_bytes,
+        DataHeaderError::MissingStringSectionOffsetValue,
+    )?;
+    let string_count_field = decode_header_field::<StringCountType>(
+        &mut decoder,
+        "string_count",
+        swap_bytes,
+        DataHeaderError::MissingStringCountValue,
+    )?;
+    let bucket_count_field = decode_header_field::<BucketCountType>(
+        &mut decoder,
+        "bucket_count",
+        swap_bytes,
+        DataHeaderError::MissingBucketCountValue,
+    )?;
+    let max_value_length_field = decode_header_field::<MaxValueLength>(
+        &mut decoder,
+        "max_value_length",
+        swap_bytes,
+        DataHeaderError::MissingMaxValueLengthValue,
+    )?;
+
+    let header = DataHeader {
+        magic: MAGIC_NATIVE_ENDIAN,
+        version: version_field.value as VersionType,
+        reserved: reserved_field.value as ReservedType,
+        string_section_offset: string_section_offset_field.value,
+        string_count: string_count_field.value,
+        bucket_count: bucket_count_field.value,
+        max_value_length: max_value_length_field.value,
+    };
+
+    let fitting_bucket_count = (bytes.len() - DataHeader::packed_size()) / Bucket::packed_size();
+    let buckets: Vec<_> = (0..header.bucket_count)
+        .take(fitting_bucket_count)
+        .map(|bucket_index| inspect_bucket(bytes, &header, bucket_index, swap_bytes))
+        .collect();
+    let unused_string_ranges = unused_string_ranges(bytes, &header, &buckets);
+
+    Ok(HeaderMapLayout {
+        swap_bytes,
+        header_fields: vec![
+            magic_field,
+            version_field,
+            reserved_field,
+            string_section_offset_field,
+            string_count_field,
+            bucket_count_field,
+            max_value_length_field,
+        ],
+        buckets,
+        unused_string_ranges,
+    })
+}
+
+fn decode_header_field<T>(
+    decoder: &mut ByteDecoder,
+    name: &'static str,
+    swap_bytes: bool,
+    missing_error: DataHeaderError,
+) -> std::result::Result<HeaderFieldLayout, DataHeaderError>
+where
+    T: DecodablePrimitive + ByteSwappable + Into<u32>,
+{
+    let offset = decoder.offset;
+    let raw_value = decoder.advance::<T>().ok_or(missing_error)?;
+    Ok(HeaderFieldLayout {
+        name,
+        offset,
+        size: std::mem::size_of::<T>(),
+        raw_value: raw_value.into(),
+        value: raw_value.swap_bytes_if(swap_bytes).into(),
+    })
+}
+
+/// Decodes a bucket which is known to fit into `bytes`.
+fn inspect_bucket<'a>(
+    bytes: &'a [u8],
+    header: &DataHeader,
+    bucket_index: BucketCountType,
+    swap_bytes: bool,
+) -> BucketLayout<'a> {
+    let offset = DataHeader::packed_size() + (bucket_index as usize) * Bucket::packed_size();
+    let mut decoder = ByteDecoder { bytes, offset };
+    let mut inspect_string_offset = || {
+        let field_offset = decoder.offset;
+        let raw_value = decoder
+            .advance::<StringSectionOffsetType>()
+            .expect("bucket fits into bytes");
+        StringOffsetLayout {
+            field_offset,
+            raw_value,
+            value: raw_value.swap_bytes_if(swap_bytes),
+            string: None,
+        }
+    };
+
+    let mut key = inspect_string_offset();
+    let mut prefix = inspect_string_offset();
+    let mut suffix = inspect_string_offset();
+    if !key.is_reserved() {
+        for string_offset in [&mut key, &mut prefix, &mut suffix] {
+            string_offset.string = Some(bucket::get_string_slice_at_offset(
+                bytes,
+                header,
+                bucket_index,
+                string_offset.value,
+            ));
+        }
+    }
+    BucketLayout {
+        index: bucket_index,
+        offset,
+        key,
+        prefix,
+        suffix,
+    }
+}
+
+fn unused_string_ranges(
+    bytes: &[u8],
+    header: &DataHeader,
+    buckets: &[BucketLayout],
+) -> Vec<Range<usize>> {
+    let string_section_offset = header.string_section_offset as usize;
+    if string_section_offset >= bytes.len() {
+        return Vec::new();
+    }
+
+    let mut used = vec![false; bytes.len() - string_section_offset];
+    if bytes[string_section_offset] == 0x0 {
+        used[0] = true;
+    }
+    for bucket in buckets {
+        for string_offset in bucket.string_offsets() {
+            if let Some(Ok(string)) = string_offset.string {
+                let start = string_offset.value as usize;
+                used[start..=start + string.len()].fill(true);
+            }
+        }
+    }
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (relative_offset, _) in used.iter().enumerate().filter(|(_, used)| !**used) {
+        let offset = string_section_offset + relative_offset;
+        match ranges.last_mut() {
+            Some(range) if range.end == offset => range.end += 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+    ranges
+}
+
+/// Prints the header fields, all buckets and the unused string section
+/// ranges, annotated with their byte offsets.
+pub fn print_layout<W>(writer: &mut W, layout: &HeaderMapLayout, bytes: &[u8]) -> Result<()>
+where
+    W: std::io::Write,
+{
+    let byte_order = if layout.swap_bytes {
+        "byte swapped"
+    } else {
+        "native byte order"
+    };
+    writeln!(writer, "Header ({}):", byte_order)?;
+    for field in &layout.header_fields {
+        writeln!(
+            writer,
+            "  0x{:08x} {:<21} {:<10} (raw 0x{:0width$x})",
+            field.offset,
+            field.name,
+            field.value,
+            field.raw_value,
+            width = field.size * 2
+        )?;
+    }
+
+    let bucket_count = layout
+        .header_field("bucket_count")
+        .map_or(0, |field| field.value as usize);
+    writeln!(writer, "Buckets ({}):", bucket_count)?;
+    for bucket in &layout.buckets {
+        if bucket.is_empty() {
+            write!(
+                writer,
+                "  0x{:08x} #{}: empty (key offset reserved)",
+                bucket.offset, bucket.index
+            )?;
+            if !bucket.prefix.is_reserved() || !bucket.suffix.is_reserved() {
+                write!(
+                    writer,
+                    ", ignored prefix {} and suffix {}",
+                    bucket.prefix.value, bucket.suffix.value
+                )?;
+            }
+            writeln!(writer)?;
+            continue;
+        }
+
+        writeln!(writer, "  0x{:08x} #{}:", bucket.offset, bucket.index)?;
+        for (name, string_offset) in ["key", "prefix", "suffix"]
+            .into_iter()
+            .zip(bucket.string_offsets())
+        {
+            write!(
+                writer,
+                "    0x{:08x} {:<6} {}",
+                string_offset.field_offset, name, string_offset.value
+            )?;
+            if layout.swap_bytes {
+                write!(writer, " (raw 0x{:08x})", string_offset.raw_value)?;
+            }
+            match &string_offset.string {
+                None => writeln!(writer)?,
+                Some(Ok(string)) => writeln!(writer, " -> {:?}", string)?,
+                Some(Err(error)) => writeln!(writer, " -> {}", error.kind)?,
+            }
+        }
+    }
+    if layout.buckets.len() < bucket_count {
+        writeln!(
+            writer,
+            "  {} bucket(s) do not fit into the file",
+            bucket_count - layout.buckets.len()
+        )?;
+    }
+
+    writeln!(writer, "Unused string section ranges:")?;
+    if layout.unused_string_ranges.is_empty() {
+        writeln!(writer, "  none")?;
+    }
+    for range in &layout.unused_string_ranges {
+        let preview_end = range.end.min(range.start + MAX_PREVIEW_LENGTH);
+        let preview = String::from_utf8_lossy(&bytes[range.start..preview_end]);
+        writeln!(
+            writer,
+            "  0x{:08x}..0x{:08x} ({} bytes): {:?}{}",
+            range.start,
+            range.end,
+            range.len(),
+            preview,
+            if preview_end < range.end { "..." } else { "" }
+        )?;
+    }
+
+    Ok(())
+}
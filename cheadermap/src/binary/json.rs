@@ -1,50 +1,481 @@
-use crate::binary::Entry;
+use crate::binary::{
+    json_value::{parse_json, JsonSyntaxError, JsonValue},
+    DataHeaderParseResult, Entry, HeaderMapDiff, HeaderMapStats, OwnedEntry, Result,
+};
 
-// NB: Avoid pulling in additional crate deps to just print out a JSON dict
-pub fn print_json_entries<W>(writer: &mut W, entries: &[Entry], tab_size: u8) -> anyhow::Result<()>
+const HMAPTOOL_MAPPINGS_KEY: &str = "mappings";
+
+/// The shape of the JSON entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// `{"key": {"prefix": "...", "suffix": "..."}}`, matches the output
+    /// of the Swift `hmap` tool.
+    Structured,
+    /// `{"key": "prefix + suffix"}`
+    Flat,
+}
+
+// NB: Printed by hand (rather than via `serde_json`) to keep the exact output format stable
+pub fn print_json_entries<W>(
+    writer: &mut W,
+    entries: &[Entry],
+    style: JsonStyle,
+    header: Option<&DataHeaderParseResult>,
+    tab_size: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    match header {
+        Some(header) => {
+            writeln!(writer, "{{")?;
+            write_indent(writer, tab_size)?;
+            write!(writer, "\"header\": ")?;
+            write_json_header(writer, header, tab_size, 1)?;
+            writeln!(writer, ",")?;
+            write_indent(writer, tab_size)?;
+            write!(writer, "\"entries\": ")?;
+            write_json_entries(writer, entries, style, tab_size, 1)?;
+            writeln!(writer)?;
+            writeln!(writer, "}}")?;
+        }
+        None => {
+            write_json_entries(writer, entries, style, tab_size, 0)?;
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_indent<W>(writer: &mut W, indent_size: usize) -> Result<()>
+where
+    W: std::io::Write,
+{
+    write!(writer, "{:indent_size$}", "")?;
+    Ok(())
+}
+
+fn write_json_header<W>(
+    writer: &mut W,
+    parse_result: &DataHeaderParseResult,
+    tab_size: usize,
+    depth: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    let header = &parse_result.header;
+    let fields = [
+        ("magic", header.magic),
+        ("version", header.version as u32),
+        ("reserved", header.reserved as u32),
+        ("string_section_offset", header.string_section_offset),
+        ("string_count", header.string_count),
+        ("bucket_count", header.bucket_count),
+        ("max_value_length", header.max_value_length),
+    ];
+
+    writeln!(writer, "{{")?;
+    for (name, value) in fields {
+        write_indent(writer, tab_size * (depth + 1))?;
+        writeln!(writer, "\"{}\": {},", name, value)?;
+    }
+    write_indent(writer, tab_size * (depth + 1))?;
+    writeln!(writer, "\"swap_bytes\": {}", parse_result.swap_bytes)?;
+    write_indent(writer, tab_size * depth)?;
+    write!(writer, "}}")?;
+
+    Ok(())
+}
+
+fn write_json_entries<W>(
+    writer: &mut W,
+    entries: &[Entry],
+    style: JsonStyle,
+    tab_size: usize,
+    depth: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    let members: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.key, Some(*entry)))
+        .collect();
+    write_json_members(writer, &members, style, tab_size, depth)
+}
+
+/// Writes an object mapping each key to the target of its entry, or to
+/// `null` if there's no entry.
+fn write_json_members<W>(
+    writer: &mut W,
+    members: &[(&str, Option<Entry>)],
+    style: JsonStyle,
+    tab_size: usize,
+    depth: usize,
+) -> Result<()>
 where
     W: std::io::Write,
 {
     writeln!(writer, "{{")?;
 
-    for (index, entry) in entries.iter().enumerate() {
-        for _ in 0..tab_size {
-            write!(writer, " ")?;
+    for (index, (key, entry)) in members.iter().enumerate() {
+        write_indent(writer, tab_size * (depth + 1))?;
+        write_json_string(writer, &[key])?;
+        write!(writer, ": ")?;
+
+        match (style, entry) {
+            (_, None) => write!(writer, "null")?,
+            (JsonStyle::Structured, Some(entry)) => {
+                write_json_target(writer, entry.prefix, entry.suffix, tab_size, depth + 1)?;
+            }
+            (JsonStyle::Flat, Some(entry)) => {
+                write_json_string(writer, &[entry.prefix, entry.suffix])?;
+            }
         }
 
-        write!(writer, "\"")?;
-        write_json_escaped_string(writer, entry.key)?;
-        write!(writer, "\": ")?;
+        if index != members.len() - 1 {
+            write!(writer, ",")?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    write_indent(writer, tab_size * depth)?;
+    write!(writer, "}}")?;
 
-        write!(writer, "\"")?;
-        write_json_escaped_string(writer, entry.prefix)?;
-        write_json_escaped_string(writer, entry.suffix)?;
-        write!(writer, "\"")?;
+    Ok(())
+}
 
-        if index != entries.len() - 1 {
+/// Writes `{"prefix": "...", "suffix": "..."}`, where `depth` is the
+/// indentation depth of the opening brace.
+fn write_json_target<W>(
+    writer: &mut W,
+    prefix: &str,
+    suffix: &str,
+    tab_size: usize,
+    depth: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "{{")?;
+    write_indent(writer, tab_size * (depth + 1))?;
+    write!(writer, "\"prefix\": ")?;
+    write_json_string(writer, &[prefix])?;
+    writeln!(writer, ",")?;
+    write_indent(writer, tab_size * (depth + 1))?;
+    write!(writer, "\"suffix\": ")?;
+    write_json_string(writer, &[suffix])?;
+    writeln!(writer)?;
+    write_indent(writer, tab_size * depth)?;
+    write!(writer, "}}")?;
+    Ok(())
+}
+
+/// Prints the diff as `{"added": {...}, "removed": {...}, "changed": {...}}`.
+/// Added and removed entries use the structured entry format, changed
+/// entries map each key to its `old` and `new` target together with
+/// `prefix_changed` and `suffix_changed` flags.
+pub fn print_json_diff<W>(writer: &mut W, diff: &HeaderMapDiff, tab_size: usize) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "{{")?;
+    for (name, entries) in [("added", &diff.added), ("removed", &diff.removed)] {
+        let borrowed_entries: Vec<_> = entries.iter().map(OwnedEntry::as_entry).collect();
+        write_indent(writer, tab_size)?;
+        write!(writer, "\"{}\": ", name)?;
+        write_json_entries(
+            writer,
+            &borrowed_entries,
+            JsonStyle::Structured,
+            tab_size,
+            1,
+        )?;
+        writeln!(writer, ",")?;
+    }
+
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "\"changed\": {{")?;
+    for (index, changed_entry) in diff.changed.iter().enumerate() {
+        write_indent(writer, tab_size * 2)?;
+        write_json_string(writer, &[&changed_entry.old.key])?;
+        writeln!(writer, ": {{")?;
+        for (name, entry) in [("old", &changed_entry.old), ("new", &changed_entry.new)] {
+            write_indent(writer, tab_size * 3)?;
+            write!(writer, "\"{}\": ", name)?;
+            write_json_target(writer, &entry.prefix, &entry.suffix, tab_size, 3)?;
+            writeln!(writer, ",")?;
+        }
+        write_indent(writer, tab_size * 3)?;
+        writeln!(
+            writer,
+            "\"prefix_changed\": {},",
+            changed_entry.prefix_changed()
+        )?;
+        write_indent(writer, tab_size * 3)?;
+        writeln!(
+            writer,
+            "\"suffix_changed\": {}",
+            changed_entry.suffix_changed()
+        )?;
+        write_indent(writer, tab_size * 2)?;
+        write!(writer, "}}")?;
+        if index != diff.changed.len() - 1 {
             write!(writer, ",")?;
         }
+        writeln!(writer)?;
+    }
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "}}")?;
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
+
+/// Prints the stats as a JSON object. The probe length histogram maps
+/// probe lengths (as strings) to entry counts and the prefixes map each
+/// prefix to its entry count, in descending order.
+pub fn print_json_stats<W>(writer: &mut W, stats: &HeaderMapStats, tab_size: usize) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "{{")?;
+    write_indent(writer, tab_size)?;
+    write!(writer, "\"header\": ")?;
+    write_json_header(writer, &stats.header, tab_size, 1)?;
+    writeln!(writer, ",")?;
+
+    let fields = [
+        ("entry_count", stats.entry_count.to_string()),
+        ("bucket_count", stats.bucket_count().to_string()),
+        ("load_factor", stats.load_factor().to_string()),
+        ("max_probe_length", stats.max_probe_length().to_string()),
+        ("longest_cluster", stats.longest_cluster.to_string()),
+        ("string_section_size", stats.string_section_size.to_string()),
+        (
+            "referenced_string_bytes",
+            stats.referenced_string_bytes.to_string(),
+        ),
+        (
+            "undeduplicated_string_bytes",
+            stats.undeduplicated_string_bytes.to_string(),
+        ),
+        (
+            "deduplication_savings",
+            stats.deduplication_savings().to_string(),
+        ),
+    ];
+    for (name, value) in fields {
+        write_indent(writer, tab_size)?;
+        writeln!(writer, "\"{}\": {},", name, value)?;
+    }
 
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "\"probe_length_histogram\": {{")?;
+    for (index, (probe_length, entry_count)) in stats.probe_length_histogram.iter().enumerate() {
+        write_indent(writer, tab_size * 2)?;
+        write!(writer, "\"{}\": {}", probe_length, entry_count)?;
+        if index != stats.probe_length_histogram.len() - 1 {
+            write!(writer, ",")?;
+        }
         writeln!(writer)?;
     }
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "}},")?;
 
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "\"prefixes\": {{")?;
+    for (index, prefix_stats) in stats.prefixes.iter().enumerate() {
+        write_indent(writer, tab_size * 2)?;
+        write_json_string(writer, &[&prefix_stats.prefix])?;
+        write!(writer, ": {}", prefix_stats.entry_count)?;
+        if index != stats.prefixes.len() - 1 {
+            write!(writer, ",")?;
+        }
+        writeln!(writer)?;
+    }
+    write_indent(writer, tab_size)?;
+    writeln!(writer, "}}")?;
     writeln!(writer, "}}")?;
 
     Ok(())
 }
 
-fn write_json_escaped_string<W>(writer: &mut W, string: &str) -> anyhow::Result<()>
+/// Writes the concatenation of `strings` as a single quoted JSON string.
+fn write_json_string<W>(writer: &mut W, strings: &[&str]) -> Result<()>
+where
+    W: std::io::Write,
+{
+    write!(writer, "\"")?;
+    for string in strings {
+        write_json_escaped_string(writer, string)?;
+    }
+    write!(writer, "\"")?;
+    Ok(())
+}
+
+fn write_json_escaped_string<W>(writer: &mut W, string: &str) -> Result<()>
 where
     W: std::io::Write,
 {
     for byte in string.as_bytes() {
-        if *byte < 0x20 {
-            write!(writer, "{:04X}", *byte)?;
-        } else if *byte == b'\\' || *byte == b'"' {
-            writer.write_all(&[b'\\', *byte])?;
-        } else {
-            writer.write_all(&[*byte])?;
+        match *byte {
+            b'"' | b'\\' => writer.write_all(&[b'\\', *byte])?,
+            b'\n' => writer.write_all(b"\\n")?,
+            b'\r' => writer.write_all(b"\\r")?,
+            b'\t' => writer.write_all(b"\\t")?,
+            byte if byte < 0x20 => write!(writer, "\\u{:04X}", byte)?,
+            byte => writer.write_all(&[byte])?,
         }
     }
     Ok(())
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum JsonParseError {
+    #[error("Invalid JSON: {0}")]
+    Syntax(#[from] JsonSyntaxError),
+    #[error("Expected a JSON object mapping keys to targets")]
+    ExpectedObject,
+    #[error("Missing `mappings` object")]
+    MissingMappings,
+    #[error("Invalid target for key `{0}`, expected a string or an object with `prefix` and `suffix` strings")]
+    InvalidTarget(String),
+}
+
+/// Parses entries from a JSON object in either of the shapes printed by
+/// `print_json_entries`, i.e., `{"key": {"prefix": "...", "suffix": "..."}}`
+/// or `{"key": "path"}`, optionally nested under an `entries` key. Flat
+/// paths get split into prefix and suffix by `OwnedEntry::from_target_path`.
+pub fn parse_json_entries(bytes: &[u8]) -> Result<Vec<OwnedEntry>> {
+    let value = parse_json(bytes).map_err(JsonParseError::from)?;
+    let object = value.as_object().ok_or(JsonParseError::ExpectedObject)?;
+    match object.get("entries") {
+        Some(entries_value) if object.contains_key("header") => parse_json_mappings(entries_value),
+        _ => parse_json_mappings(&value),
+    }
+}
+
+/// Parses entries from the JSON format used by LLVM's `hmaptool`,
+/// i.e., `{"mappings": {"key": "path"}}`.
+pub fn parse_hmaptool_json_entries(bytes: &[u8]) -> Result<Vec<OwnedEntry>> {
+    let value = parse_json(bytes).map_err(JsonParseError::from)?;
+    let mappings = value
+        .get(HMAPTOOL_MAPPINGS_KEY)
+        .ok_or(JsonParseError::MissingMappings)?;
+    parse_json_mappings(mappings)
+}
+
+/// Prints the entries in the JSON format used by LLVM's `hmaptool`.
+pub fn print_hmaptool_json_entries<W>(
+    writer: &mut W,
+    entries: &[Entry],
+    tab_size: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "{{")?;
+    write_indent(writer, tab_size)?;
+    write!(writer, "\"{}\": ", HMAPTOOL_MAPPINGS_KEY)?;
+    write_json_entries(writer, entries, JsonStyle::Flat, tab_size, 1)?;
+    writeln!(writer)?;
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+/// Prints the lookup results as a JSON object keyed by the looked up keys,
+/// misses map to `null`. Keys looked up repeatedly are printed as
+/// repeated members. If `hmaptool` is set, the object gets nested
+/// under a `mappings` key like in `print_hmaptool_json_entries`.
+pub fn print_json_lookup_results<W>(
+    writer: &mut W,
+    results: &[(&str, Option<Entry>)],
+    style: JsonStyle,
+    hmaptool: bool,
+    tab_size: usize,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    if !hmaptool {
+        write_json_members(writer, results, style, tab_size, 0)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+
+    writeln!(writer, "{{")?;
+    write_indent(writer, tab_size)?;
+    write!(writer, "\"{}\": ", HMAPTOOL_MAPPINGS_KEY)?;
+    write_json_members(writer, results, style, tab_size, 1)?;
+    writeln!(writer)?;
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+fn parse_json_mappings(value: &JsonValue) -> Result<Vec<OwnedEntry>> {
+    let entries_object = value.as_object().ok_or(JsonParseError::ExpectedObject)?;
+
+    let mut entries = Vec::new();
+    for (key, target) in entries_object {
+        let entry = match target {
+            JsonValue::String(target_path) => OwnedEntry::from_target_path(key, target_path),
+            JsonValue::Object(target_object) => {
+                let prefix = target_object.get("prefix").and_then(|value| value.as_str());
+                let suffix = target_object.get("suffix").and_then(|value| value.as_str());
+                match (prefix, suffix) {
+                    (Some(prefix), Some(suffix)) => OwnedEntry::new(key.as_str(), prefix, suffix),
+                    _ => return Err(JsonParseError::InvalidTarget(key.clone()).into()),
+                }
+            }
+            _ => return Err(JsonParseError::InvalidTarget(key.clone()).into()),
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaped_string() {
+        let mut output = Vec::new();
+        write_json_string(&mut output, &["a\"b\\c\n", "\u{1}d/é"]).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\"a\\\"b\\\\c\\n\\u0001d/é\""
+        );
+    }
+
+    #[test]
+    fn test_parse_json_entries() {
+        let json = br#"{
+            "Flat.h": "/path/to/Flat.h",
+            "NoSlash.h": "NoSlash.h",
+            "Structured.h": {"prefix": "/path/", "suffix": "to/Structured.h"}
+        }"#;
+        let entries = parse_json_entries(json).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                OwnedEntry::new("Flat.h", "/path/to/", "Flat.h"),
+                OwnedEntry::new("NoSlash.h", "", "NoSlash.h"),
+                OwnedEntry::new("Structured.h", "/path/", "to/Structured.h"),
+            ]
+        );
+
+        let hmaptool_json = br#"{"mappings": {"Flat.h": "/path/to/Flat.h"}}"#;
+        let hmaptool_entries = parse_hmaptool_json_entries(hmaptool_json).unwrap();
+        assert_eq!(hmaptool_entries, entries[0..1]);
+
+        assert!(parse_json_entries(br#"["Flat.h"]"#).is_err());
+        assert!(parse_hmaptool_json_entries(br#"{"Flat.h": "/path/to/Flat.h"}"#).is_err());
+        assert!(parse_json_entries(br#"{"Invalid.h": {"prefix": "/path/"}}"#).is_err());
+    }
+}
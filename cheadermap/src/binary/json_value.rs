@@ -0,0 +1,388 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::BTreeMap;
+
+/// Arrays and objects nested deeper than this are rejected, so malicious
+/// input cannot overflow the stack.
+const MAX_NESTING_DEPTH: usize = 128;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} at byte {offset}")]
+pub struct JsonSyntaxError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+/// A parsed JSON value. Like in `serde_json`, object members are sorted
+/// by key and the last of several members with the same key wins.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+impl JsonValue {
+    /// Returns the value of the member `key` if `self` is an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?.get(key)
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&BTreeMap<String, JsonValue>> {
+        match self {
+            JsonValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document (RFC 8259), surrounding whitespace is allowed.
+pub(crate) fn parse_json(bytes: &[u8]) -> Result<JsonValue, JsonSyntaxError> {
+    let text = std::str::from_utf8(bytes).map_err(|utf8_error| JsonSyntaxError {
+        offset: utf8_error.valid_up_to(),
+        message: "Invalid UTF-8",
+    })?;
+    let mut parser = JsonParser {
+        text,
+        offset: 0,
+        depth: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.offset != text.len() {
+        return Err(parser.error("Trailing characters"));
+    }
+    Ok(value)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    offset: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, message: &'static str) -> JsonSyntaxError {
+        JsonSyntaxError {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    /// Consumes `byte` after skipping whitespace, returns `false` if the
+    /// next byte is a different one.
+    fn consume(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), JsonSyntaxError> {
+        if self.consume(byte) {
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => self.parse_literal(),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn enter_nested_value(&mut self) -> Result<(), JsonSyntaxError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(self.error("Nesting too deep"));
+        }
+        self.offset += 1;
+        Ok(())
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        self.enter_nested_value()?;
+        let mut object = BTreeMap::new();
+        if !self.consume(b'}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return Err(self.error("Expected a string key"));
+                }
+                let key = self.parse_string()?;
+                self.expect(b':', "Expected `:`")?;
+                let value = self.parse_value()?;
+                object.insert(key, value);
+                if self.consume(b'}') {
+                    break;
+                }
+                self.expect(b',', "Expected `,` or `}`")?;
+            }
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Object(object))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        self.enter_nested_value()?;
+        let mut array = Vec::new();
+        if !self.consume(b']') {
+            loop {
+                array.push(self.parse_value()?);
+                if self.consume(b']') {
+                    break;
+                }
+                self.expect(b',', "Expected `,` or `]`")?;
+            }
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Array(array))
+    }
+
+    /// Parses the string starting at the current `"`.
+    fn parse_string(&mut self) -> Result<String, JsonSyntaxError> {
+        self.offset += 1;
+        let mut string = String::new();
+        loop {
+            let remaining = &self.text[self.offset..];
+            let special_index = remaining
+                .find(|character: char| character == '"' || character == '\\' || character < ' ')
+                .ok_or(JsonSyntaxError {
+                    offset: self.text.len(),
+                    message: "Unterminated string",
+                })?;
+            string.push_str(&remaining[..special_index]);
+            self.offset += special_index;
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.offset += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') => {
+                    self.offset += 1;
+                    string.push(self.parse_escape()?);
+                }
+                _ => return Err(self.error("Control character in string")),
+            }
+        }
+    }
+
+    /// Parses the escape sequence after a `\`.
+    fn parse_escape(&mut self) -> Result<char, JsonSyntaxError> {
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("Unterminated string"))?;
+        self.offset += 1;
+        let character = match escaped {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let code_unit = self.parse_hex_code_unit()?;
+                let code_point = match code_unit {
+                    0xD800..=0xDBFF => {
+                        if !self.text[self.offset..].starts_with("\\u") {
+                            return Err(self.error("Unpaired surrogate"));
+                        }
+                        self.offset += 2;
+                        let low_code_unit = self.parse_hex_code_unit()?;
+                        if !(0xDC00..=0xDFFF).contains(&low_code_unit) {
+                            return Err(self.error("Unpaired surrogate"));
+                        }
+                        0x10000 + ((code_unit - 0xD800) << 10) + (low_code_unit - 0xDC00)
+                    }
+                    0xDC00..=0xDFFF => return Err(self.error("Unpaired surrogate")),
+                    code_unit => code_unit,
+                };
+                char::from_u32(code_point).ok_or_else(|| self.error("Invalid code point"))?
+            }
+            _ => {
+                self.offset -= 1;
+                return Err(self.error("Invalid escape sequence"));
+            }
+        };
+        Ok(character)
+    }
+
+    fn parse_hex_code_unit(&mut self) -> Result<u32, JsonSyntaxError> {
+        let hex_digits = self
+            .text
+            .get(self.offset..self.offset + 4)
+            .filter(|hex_digits| hex_digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Expected four hex digits"))?;
+        self.offset += 4;
+        Ok(u32::from_str_radix(hex_digits, 16).expect("hex digits"))
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        let start_offset = self.offset;
+        let skip_digits = |parser: &mut JsonParser| {
+            let digits_offset = parser.offset;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.offset += 1;
+            }
+            parser.offset > digits_offset
+        };
+
+        if self.peek() == Some(b'-') {
+            self.offset += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.offset += 1;
+        } else if !skip_digits(self) {
+            return Err(self.error("Expected a digit"));
+        }
+        if self.peek() == Some(b'.') {
+            self.offset += 1;
+            if !skip_digits(self) {
+                return Err(self.error("Expected a digit"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.offset += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.offset += 1;
+            }
+            if !skip_digits(self) {
+                return Err(self.error("Expected a digit"));
+            }
+        }
+
+        let number = self.text[start_offset..self.offset]
+            .parse()
+            .expect("valid JSON number");
+        Ok(JsonValue::Number(number))
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        for (literal, value) in [
+            ("null", JsonValue::Null),
+            ("true", JsonValue::Bool(true)),
+            ("false", JsonValue::Bool(false)),
+        ] {
+            if self.text[self.offset..].starts_with(literal) {
+                self.offset += literal.len();
+                return Ok(value);
+            }
+        }
+        Err(self.error("Expected a value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let value = parse_json(
+            br#" {"b": [1, -2.5e3, true, false, null], "a": "x\"\\\/\n\u00e9\ud83d\ude00", "a": "y"} "#,
+        )
+        .unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(value.get("a").unwrap().as_str(), Some("y"));
+        assert_eq!(
+            value.get("b").unwrap().as_array().unwrap(),
+            &vec![
+                JsonValue::Number(1.0),
+                JsonValue::Number(-2500.0),
+                JsonValue::Bool(true),
+                JsonValue::Bool(false),
+                JsonValue::Null,
+            ]
+        );
+        assert_eq!(
+            parse_json(br#""x\"\\\/\n\u00e9\ud83d\ude00""#).unwrap(),
+            JsonValue::String("x\"\\/\n\u{e9}\u{1F600}".to_owned())
+        );
+        assert_eq!(parse_json(b"[]").unwrap(), JsonValue::Array(Vec::new()));
+        assert_eq!(
+            parse_json(b"{}").unwrap(),
+            JsonValue::Object(BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_json() {
+        for (json, offset) in [
+            (&b""[..], 0),
+            (b"{", 1),
+            (b"{\"a\" 1}", 5),
+            (b"{\"a\": 1,}", 8),
+            (b"[1 2]", 3),
+            (b"[01]", 2),
+            (b"[1.]", 3),
+            (b"\"a", 2),
+            (b"\"\\x\"", 2),
+            (b"\"\\ud83d\"", 7),
+            (b"\"\t\"", 1),
+            (b"nul", 0),
+            (b"{} {}", 3),
+            (b"\"\xff\"", 1),
+        ] {
+            assert_eq!(
+                parse_json(json).map_err(|error| error.offset),
+                Err(offset),
+                "{:?}",
+                String::from_utf8_lossy(json)
+            );
+        }
+
+        let deeply_nested = "[".repeat(MAX_NESTING_DEPTH + 1);
+        assert_eq!(
+            parse_json(deeply_nested.as_bytes()).unwrap_err().message,
+            "Nesting too deep"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::HashMap;
+
+use crate::binary::{
+    entry::{Entry, OwnedEntry},
+    error::Result,
+};
+
+/// How to resolve entries whose keys collide when merging headermaps.
+/// Entries whose keys match case-insensitively (like Clang) but which
+/// map to different paths are in conflict. Entries mapping to the same
+/// path are never in conflict, the first one is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// The entry from the earliest input wins.
+    FirstWins,
+    /// The entry from the latest input wins.
+    LastWins,
+    /// Conflicting entries with identical keys are an error. Keys which
+    /// only differ in case get resolved like `FirstWins`.
+    Error,
+    /// Any conflicting entries are an error.
+    CaseInsensitiveError,
+}
+
+/// An entry together with the index of the input it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcedEntry {
+    pub input_index: usize,
+    pub entry: OwnedEntry,
+}
+
+/// Entries which were in conflict and how they got resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// The entry written to the merged headermap.
+    pub winner: SourcedEntry,
+    /// The entries which got dropped, in input order.
+    pub losers: Vec<SourcedEntry>,
+}
+
+/// The result of merging headermaps.
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    /// The merged entries, in order of first appearance of their key.
+    pub entries: Vec<SourcedEntry>,
+    /// The conflicts resolved by the merge policy.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MergeError {
+    #[error(
+        "Conflicting entries from inputs `{}` and `{}`: `{}` and `{}`",
+        .existing.input_index,
+        .conflicting.input_index,
+        .existing.entry,
+        .conflicting.entry
+    )]
+    Conflict {
+        existing: Box<SourcedEntry>,
+        conflicting: Box<SourcedEntry>,
+    },
+}
+
+/// Merges the entries of several headermaps, resolving key conflicts
+/// according to `policy`. `inputs` are the entries of each headermap,
+/// e.g., as returned by `parse_headermap`.
+pub fn merge_entries(inputs: &[Vec<Entry>], policy: MergePolicy) -> Result<MergeResult> {
+    let mut groups: Vec<Vec<SourcedEntry>> = Vec::new();
+    let mut group_indices = HashMap::new();
+    for (input_index, entries) in inputs.iter().enumerate() {
+        for entry in entries {
+            let group_index = *group_indices
+                .entry(entry.key.to_ascii_lowercase())
+                .or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+            groups[group_index].push(SourcedEntry {
+                input_index,
+                entry: entry.to_owned_entry(),
+            });
+        }
+    }
+
+    let mut result = MergeResult::default();
+    for mut group in groups {
+        if policy == MergePolicy::Error {
+            check_identical_key_conflicts(&group)?;
+        }
+
+        let winner = match policy {
+            MergePolicy::LastWins => group.pop(),
+            _ => Some(group.remove(0)),
+        }
+        .expect("groups are never empty");
+
+        let winner_path = winner.entry.target_path();
+        let losers: Vec<_> = group
+            .into_iter()
+            .filter(|sourced_entry| sourced_entry.entry.target_path() != winner_path)
+            .collect();
+
+        if let Some(conflicting) = losers.first() {
+            if policy == MergePolicy::CaseInsensitiveError {
+                return Err(MergeError::Conflict {
+                    existing: Box::new(winner),
+                    conflicting: Box::new(conflicting.clone()),
+                }
+                .into());
+            }
+
+            result.conflicts.push(MergeConflict {
+                winner: winner.clone(),
+                losers,
+            });
+        }
+
+        result.entries.push(winner);
+    }
+
+    Ok(result)
+}
+
+/// Returns an error for the first pair of entries with identical keys
+/// which map to different paths.
+fn check_identical_key_conflicts(group: &[SourcedEntry]) -> std::result::Result<(), MergeError> {
+    for (index, existing) in group.iter().enumerate() {
+        let conflicting = group[index + 1..].iter().find(|sourced_entry| {
+            sourced_entry.entry.key == existing.entry.key
+                && sourced_entry.entry.target_path() != existing.entry.target_path()
+        });
+        if let Some(conflicting) = conflicting {
+            return Err(MergeError::Conflict {
+                existing: Box::new(existing.clone()),
+                conflicting: Box::new(conflicting.clone()),
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -2,13 +2,70 @@
 
 mod bucket;
 mod byte_decoder;
+mod byte_encoder;
+mod diff;
+mod edit;
+mod entries;
 mod entry;
+mod error;
+mod expand;
+mod filter;
+mod generate;
+mod hash;
 mod header;
+mod headermap;
+mod inspect;
 mod json;
-mod types;
+mod json_value;
+mod merge;
+mod params;
+mod remap;
+mod repair;
+mod reverse;
+mod stats;
+mod trace;
+pub mod types;
 mod utility;
+mod validate;
+mod writer;
+mod xcode;
 
-pub use entry::Entry;
+pub use diff::{diff_entries, print_diff, ChangedEntry, DiffFormat, HeaderMapDiff};
+pub use edit::{edit_headermap, parse_edit_script, EditError, EditOperation, EditOutcome};
+pub use entries::{entries, Entries};
+pub use entry::{Entry, OwnedEntry};
+pub use error::{BucketError, BucketErrorKind, Error, Result, StringError};
+pub use expand::{expand_entries, parse_variable_definition, parse_variable_file, ExpandError};
+pub use filter::{parse_key_list, EntryFilter, FilterError};
+pub use generate::{
+    generate_headermap_entries, GenerateError, GenerateOptions, GeneratedEntries,
+    DEFAULT_HEADER_EXTENSIONS,
+};
+pub use hash::{bucket_index_for_key, hash_key};
+pub use header::{parse_header, DataHeader, DataHeaderError, DataHeaderParseResult};
+pub use headermap::{HeaderMap, Iter};
+pub use inspect::{
+    inspect_headermap, print_layout, BucketLayout, HeaderFieldLayout, HeaderMapLayout,
+    StringOffsetLayout,
+};
+pub use json::{parse_hmaptool_json_entries, parse_json_entries, JsonParseError};
+pub use json_value::JsonSyntaxError;
+pub use merge::{merge_entries, MergeConflict, MergeError, MergePolicy, MergeResult, SourcedEntry};
+pub use params::{parse_params_entries, unrepresentable_params_entries, ParamsError};
+pub use remap::{remap_entries, remap_headermap, RemapOptions, RemapRule};
+pub use repair::{repair_headermap, RepairReport};
+pub use reverse::{normalize_path, ReverseIndex};
+pub use stats::{
+    headermap_stats, headermap_stats_lenient, print_stats, HeaderMapStats, PrefixStats, StatsFormat,
+};
+pub use trace::{print_lookup_trace, LookupOutcome, LookupTrace, ProbedBucket, ProbedBucketState};
+pub use validate::{validate_headermap, Finding, FindingKind, Severity};
+pub use writer::{build_headermap, HeaderMapBuilder, HeaderMapWriteError};
+pub use xcode::{
+    generate_xcode_headermap, generate_xcode_headermaps, parse_target_descriptions,
+    HeaderVisibility, TargetDescription, TargetDescriptionError, TargetHeader, XcodeHeaderMap,
+    XcodeHeaderMapKind,
+};
 
 /// Enumerates the entries in the headermap. If the header cannot be parsed,
 /// returns an error. If `fail_on_bucket_error` is `true`, then on the first
@@ -20,34 +77,21 @@ pub fn headermap_enumerate_entries<'a, F>(
     bytes: &'a [u8],
     fail_on_bucket_error: bool,
     mut enumerator: F,
-) -> anyhow::Result<()>
+) -> Result<()>
 where
     F: FnMut(Entry<'a>) -> bool,
 {
-    let parse_result = header::parse_header(bytes)?;
-
-    for bucket_index in 0..parse_result.header.bucket_count {
-        let entry_result = bucket::parse_entry_at_bucket_index(
-            bytes,
-            &parse_result.header,
-            bucket_index,
-            parse_result.swap_bytes,
-        );
-
+    for entry_result in entries(bytes)? {
         match entry_result {
-            Ok(maybe_entry) => {
-                // If `maybe_entry` is `None`, it means the hash bucket was empty,
-                // it's not an error condition.
-                if let Some(entry) = maybe_entry {
-                    let continue_enumerating = enumerator(entry);
-                    if !continue_enumerating {
-                        break;
-                    }
+            Ok((_, entry)) => {
+                let continue_enumerating = enumerator(entry);
+                if !continue_enumerating {
+                    break;
                 }
             }
-            Err(parse_error) => {
+            Err(bucket_error) => {
                 if fail_on_bucket_error {
-                    return Err(parse_error);
+                    return Err(bucket_error.into());
                 }
             }
         }
@@ -61,7 +105,7 @@ where
 /// a single bucket error, the method will return an error.
 /// Otherwise, any bucket errors are ignored and partial
 /// results would be returned.
-pub fn parse_headermap(bytes: &[u8], fail_on_bucket_error: bool) -> anyhow::Result<Vec<Entry>> {
+pub fn parse_headermap(bytes: &[u8], fail_on_bucket_error: bool) -> Result<Vec<Entry<'_>>> {
     let mut accumulator = Vec::new();
     headermap_enumerate_entries(bytes, fail_on_bucket_error, |entry| {
         accumulator.push(entry);
@@ -70,24 +114,67 @@ pub fn parse_headermap(bytes: &[u8], fail_on_bucket_error: bool) -> anyhow::Resu
     Ok(accumulator)
 }
 
-fn print_text_entries<W>(writer: &mut W, entries: &[Entry]) -> anyhow::Result<()>
+/// Parses a headermap, skipping any buckets which cannot be parsed.
+/// Returns the recovered entries and the errors of the skipped buckets.
+/// Only fails if the header cannot be parsed.
+pub fn parse_headermap_lenient(bytes: &[u8]) -> Result<(Vec<Entry<'_>>, Vec<BucketError>)> {
+    let mut accumulator = Vec::new();
+    let mut bucket_errors = Vec::new();
+    for entry_result in entries(bytes)? {
+        match entry_result {
+            Ok((_, entry)) => accumulator.push(entry),
+            Err(bucket_error) => bucket_errors.push(bucket_error),
+        }
+    }
+    Ok((accumulator, bucket_errors))
+}
+
+fn print_text_entries<W>(writer: &mut W, entries: &[Entry]) -> Result<()>
 where
     W: std::io::Write,
 {
     for entry in entries {
-        writeln!(writer, "{} -> {}{}", entry.key, entry.prefix, entry.suffix)?;
+        writeln!(writer, "{}", entry)?;
     }
 
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
+    /// One entry per line in the format `key -> prefix + suffix`.
     Text,
+    /// `{"key": {"prefix": "...", "suffix": "..."}}`
+    Json,
+    /// `{"key": "prefix + suffix"}`
+    JsonFlat,
+    /// `{"mappings": {"key": "prefix + suffix"}}`, as used by LLVM's `hmaptool`.
+    Hmaptool,
+    /// A rules_ios hmap params file, see `parse_params_entries`.
+    Params,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Either JSON shape printed with `OutputFormat::Json` or `OutputFormat::JsonFlat`.
     Json,
+    /// `{"mappings": {"key": "path"}}`, as used by LLVM's `hmaptool`.
+    Hmaptool,
+    /// A rules_ios hmap params file, see `parse_params_entries`.
+    Params,
+}
+
+/// Parses entries from a textual representation of a headermap.
+pub fn parse_entries(bytes: &[u8], format: InputFormat) -> Result<Vec<OwnedEntry>> {
+    match format {
+        InputFormat::Json => parse_json_entries(bytes),
+        InputFormat::Hmaptool => parse_hmaptool_json_entries(bytes),
+        InputFormat::Params => parse_params_entries(bytes),
+    }
 }
 
 /// Prints the headermap entries, one per line in the format `key -> prefix + suffix`.
-pub fn print_headermap<W, P>(writer: &mut W, path: P, format: OutputFormat) -> anyhow::Result<()>
+pub fn print_headermap<W, P>(writer: &mut W, path: P, format: OutputFormat) -> Result<()>
 where
     W: std::io::Write,
     P: AsRef<std::path::Path>,
@@ -96,8 +183,80 @@ where
     let mut entries = parse_headermap(&file_bytes, true)?;
     entries.sort_by(|lhs, rhs| lhs.key.cmp(rhs.key));
 
+    print_entries(writer, &entries, format)
+}
+
+/// Prints `entries` in the order given, using the same format as `print_headermap`.
+pub fn print_entries<W>(writer: &mut W, entries: &[Entry], format: OutputFormat) -> Result<()>
+where
+    W: std::io::Write,
+{
+    print_entries_with_header(writer, entries, None, format)
+}
+
+/// Like `print_entries`, but `Json` and `JsonFlat` output includes the
+/// data header, if provided, and nests the entries under an `entries` key.
+/// Other formats do not include the data header.
+pub fn print_entries_with_header<W>(
+    writer: &mut W,
+    entries: &[Entry],
+    header: Option<&DataHeaderParseResult>,
+    format: OutputFormat,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
+    match format {
+        OutputFormat::Text => print_text_entries(writer, entries),
+        OutputFormat::Json => {
+            json::print_json_entries(writer, entries, json::JsonStyle::Structured, header, 4)
+        }
+        OutputFormat::JsonFlat => {
+            json::print_json_entries(writer, entries, json::JsonStyle::Flat, header, 4)
+        }
+        OutputFormat::Hmaptool => json::print_hmaptool_json_entries(writer, entries, 4),
+        OutputFormat::Params => params::print_params_entries(writer, entries),
+    }
+}
+
+/// Prints the result of looking up each key in `results`, in the order
+/// given. Each line or JSON member is keyed by the looked up key, not by
+/// the key of the entry it resolved to (which can differ in case).
+/// Misses are printed as `key: not found` lines or map to `null` in JSON.
+/// The `Params` format cannot represent misses, so they're left out.
+pub fn print_lookup_results<W>(
+    writer: &mut W,
+    results: &[(&str, Option<Entry>)],
+    format: OutputFormat,
+) -> Result<()>
+where
+    W: std::io::Write,
+{
     match format {
-        OutputFormat::Text => print_text_entries(writer, &entries),
-        OutputFormat::Json => json::print_json_entries(writer, &entries, 4),
+        OutputFormat::Text => {
+            for (key, entry) in results {
+                match entry {
+                    Some(entry) => writeln!(writer, "{}", Entry { key, ..*entry })?,
+                    None => writeln!(writer, "{}: not found", key)?,
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            json::print_json_lookup_results(writer, results, json::JsonStyle::Structured, false, 4)
+        }
+        OutputFormat::JsonFlat => {
+            json::print_json_lookup_results(writer, results, json::JsonStyle::Flat, false, 4)
+        }
+        OutputFormat::Hmaptool => {
+            json::print_json_lookup_results(writer, results, json::JsonStyle::Flat, true, 4)
+        }
+        OutputFormat::Params => {
+            let entries: Vec<_> = results
+                .iter()
+                .filter_map(|(key, entry)| entry.map(|entry| Entry { key, ..entry }))
+                .collect();
+            params::print_params_entries(writer, &entries)
+        }
     }
 }
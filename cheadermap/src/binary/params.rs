@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::HashSet;
+
+use crate::binary::{entry::Entry, entry::OwnedEntry, error::Result, generate::GeneratedEntries};
+
+const NAMESPACE_OPTION: &str = "--namespace";
+const OUTPUT_OPTION: &str = "--output";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParamsError {
+    #[error("Missing value for option `{0}`")]
+    MissingOptionValue(String),
+    #[error("Unknown option `{0}`")]
+    UnknownOption(String),
+}
+
+/// Parses a rules_ios hmap params file, i.e., a Bazel `multiline` params
+/// file with one argument per line: the `--namespace <namespace>` and
+/// `--output <path>` options (either as two lines or as `--option=value`)
+/// followed by header paths.
+///
+/// Each header gets mapped by its file name and, if a namespace is given,
+/// by `<namespace>/<file name>`. If file names collide (case-insensitively,
+/// like Clang), the first header wins.
+pub fn parse_params_entries(bytes: &[u8]) -> Result<Vec<OwnedEntry>> {
+    let contents = String::from_utf8_lossy(bytes);
+    let mut arguments = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let mut namespace = None;
+    let mut header_paths = Vec::new();
+    while let Some(argument) = arguments.next() {
+        if !argument.starts_with("--") {
+            header_paths.push(argument);
+            continue;
+        }
+
+        let (option, inline_value) = match argument.split_once('=') {
+            Some((option, value)) => (option, Some(value)),
+            None => (argument, None),
+        };
+        let value = inline_value
+            .or_else(|| arguments.next())
+            .ok_or_else(|| ParamsError::MissingOptionValue(option.to_owned()))?;
+
+        match option {
+            NAMESPACE_OPTION => namespace = Some(value),
+            // The output path is only relevant when running the rules_ios tool.
+            OUTPUT_OPTION => {}
+            _ => return Err(ParamsError::UnknownOption(option.to_owned()).into()),
+        }
+    }
+
+    let mut generated_entries = GeneratedEntries::default();
+    for header_path in header_paths {
+        generated_entries.add_header(header_path, namespace);
+    }
+
+    Ok(generated_entries.entries)
+}
+
+/// Writes `entries` as a rules_ios hmap params file, see `parse_params_entries`.
+///
+/// Only entries keyed by the file name of their target or by
+/// `<namespace>/<file name>` can be represented, and a params file has
+/// a single namespace: the one of the first namespaced entry. Other
+/// entries, e.g. the ones Xcode maps by their project-relative path, are
+/// skipped, see `unrepresentable_params_entries`.
+pub fn print_params_entries<W>(writer: &mut W, entries: &[Entry]) -> Result<()>
+where
+    W: std::io::Write,
+{
+    let (namespace, representable_entries, _) = partition_params_entries(entries);
+    if let Some(namespace) = namespace {
+        writeln!(writer, "{}", NAMESPACE_OPTION)?;
+        writeln!(writer, "{}", namespace)?;
+    }
+
+    let mut target_paths = HashSet::new();
+    for entry in representable_entries {
+        let target_path = entry.target_path();
+        if target_paths.insert(target_path.clone()) {
+            writeln!(writer, "{}", target_path.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the entries which `print_params_entries` skips, in order.
+pub fn unrepresentable_params_entries<'a>(entries: &[Entry<'a>]) -> Vec<Entry<'a>> {
+    partition_params_entries(entries).2
+}
+
+/// Returns the namespace of the params file for `entries`, the entries
+/// which can be represented in it and the ones which cannot.
+fn partition_params_entries<'a>(
+    entries: &[Entry<'a>],
+) -> (Option<&'a str>, Vec<Entry<'a>>, Vec<Entry<'a>>) {
+    let mut namespace = None;
+    let mut representable_entries = Vec::new();
+    let mut unrepresentable_entries = Vec::new();
+    for entry in entries {
+        let target_path = format!("{}{}", entry.prefix, entry.suffix);
+        let file_name = &target_path[target_path.rfind('/').map_or(0, |index| index + 1)..];
+
+        let is_representable = match entry.key.strip_suffix(file_name) {
+            Some("") => true,
+            Some(key_prefix) => match (key_prefix.strip_suffix('/'), namespace) {
+                (Some(entry_namespace), None) => {
+                    namespace = Some(entry_namespace);
+                    true
+                }
+                (Some(entry_namespace), Some(namespace)) => entry_namespace == namespace,
+                (None, _) => false,
+            },
+            None => false,
+        };
+
+        if is_representable {
+            representable_entries.push(*entry);
+        } else {
+            unrepresentable_entries.push(*entry);
+        }
+    }
+
+    (namespace, representable_entries, unrepresentable_entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_round_trip() {
+        let params = b"--namespace\nFoo\n--output=out.hmap\n\n/src/Foo/A.h\n/src/Bar/a.h\nB.h\n";
+        let entries = parse_params_entries(params).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                OwnedEntry::new("A.h", "/src/Foo/", "A.h"),
+                OwnedEntry::new("Foo/A.h", "/src/Foo/", "A.h"),
+                OwnedEntry::new("B.h", "", "B.h"),
+                OwnedEntry::new("Foo/B.h", "", "B.h"),
+            ]
+        );
+
+        let borrowed_entries: Vec<_> = entries.iter().map(OwnedEntry::as_entry).collect();
+        let mut output = Vec::new();
+        print_params_entries(&mut output, &borrowed_entries).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "--namespace\nFoo\n/src/Foo/A.h\nB.h\n"
+        );
+    }
+
+    #[test]
+    fn test_params_errors() {
+        assert!(parse_params_entries(b"--namespace").is_err());
+        assert!(parse_params_entries(b"--unknown=value").is_err());
+    }
+
+    #[test]
+    fn test_print_unrepresentable_params_entries() {
+        let entries = [
+            Entry {
+                key: "Renamed.h",
+                prefix: "/src/",
+                suffix: "A.h",
+            },
+            Entry {
+                key: "Foo/B.h",
+                prefix: "/src/",
+                suffix: "B.h",
+            },
+            Entry {
+                key: "Bar/C.h",
+                prefix: "/src/",
+                suffix: "C.h",
+            },
+            Entry {
+                key: "D.h",
+                prefix: "/src/",
+                suffix: "D.h",
+            },
+            Entry {
+                key: "Foo/Sub/E.h",
+                prefix: "/src/Sub/",
+                suffix: "E.h",
+            },
+        ];
+
+        let mut output = Vec::new();
+        print_params_entries(&mut output, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "--namespace\nFoo\n/src/B.h\n/src/D.h\n"
+        );
+        let skipped_keys: Vec<_> = unrepresentable_params_entries(&entries)
+            .iter()
+            .map(|entry| entry.key)
+            .collect();
+        assert_eq!(skipped_keys, vec!["Renamed.h", "Bar/C.h", "Foo/Sub/E.h"]);
+    }
+}
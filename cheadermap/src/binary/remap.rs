@@ -0,0 +1,161 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    entry::{Entry, OwnedEntry},
+    error::Result,
+    parse_headermap,
+    reverse::normalize_path,
+    writer::build_headermap,
+};
+
+/// Rewrites targets under the directory `from` to be under `to` instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemapRule {
+    pub from: String,
+    pub to: String,
+}
+
+/// Options for `remap_entries`.
+#[derive(Debug, Clone, Default)]
+pub struct RemapOptions {
+    /// Applied in order, only the first matching rule rewrites a target.
+    pub rules: Vec<RemapRule>,
+    /// If set, absolute targets get rewritten relative to this directory
+    /// after applying the rules.
+    pub relative_to: Option<String>,
+}
+
+/// Rewrites the targets of `entries`. Directories are matched on whole
+/// path components, so `/a/b` matches `/a/b/c.h` but not `/a/bc.h`.
+///
+/// If the rewritten target still ends with the original `suffix`, only
+/// the `prefix` changes. Otherwise, the target gets split the Xcode way,
+/// see `OwnedEntry::from_target_path`.
+pub fn remap_entries(entries: &[Entry], options: &RemapOptions) -> Vec<OwnedEntry> {
+    entries
+        .iter()
+        .map(|entry| {
+            let target_path = format!("{}{}", entry.prefix, entry.suffix);
+            let mut remapped_path = options
+                .rules
+                .iter()
+                .find_map(|rule| {
+                    strip_directory(&target_path, &rule.from)
+                        .map(|rest| join_directory(&rule.to, rest))
+                })
+                .unwrap_or_else(|| target_path.clone());
+            if let Some(base) = &options.relative_to {
+                remapped_path = relative_path(&remapped_path, base);
+            }
+
+            match remapped_path.strip_suffix(entry.suffix) {
+                Some(prefix) => OwnedEntry::new(entry.key, prefix, entry.suffix),
+                None => OwnedEntry::from_target_path(entry.key, &remapped_path),
+            }
+        })
+        .collect()
+}
+
+/// Parses a headermap, rewrites its targets and builds a new headermap.
+pub fn remap_headermap(bytes: &[u8], options: &RemapOptions) -> Result<Vec<u8>> {
+    let entries = parse_headermap(bytes, true)?;
+    let remapped_entries = remap_entries(&entries, options);
+    Ok(build_headermap(
+        remapped_entries.iter().map(OwnedEntry::as_entry),
+    )?)
+}
+
+/// If `path` is `directory` or inside it, returns the rest of the path,
+/// which is either empty or starts with a `/`.
+fn strip_directory<'a>(path: &'a str, directory: &str) -> Option<&'a str> {
+    let directory = directory.trim_end_matches('/');
+    let rest = path.strip_prefix(directory)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+fn join_directory(directory: &str, rest: &str) -> String {
+    let directory = directory.trim_end_matches('/');
+    if directory.is_empty() && rest.is_empty() {
+        "/".to_owned()
+    } else {
+        format!("{}{}", directory, rest)
+    }
+}
+
+/// Returns `path` relative to `base`, both get normalized first. Paths
+/// which are not both absolute (or both relative) are returned unchanged,
+/// as are relative paths if `base` leaves the directory they're relative
+/// to, since its name is unknown.
+fn relative_path(path: &str, base: &str) -> String {
+    if path.starts_with('/') != base.starts_with('/') {
+        return path.to_owned();
+    }
+
+    let components = |path: &'_ str| -> Vec<String> {
+        normalize_path(path)
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(str::to_owned)
+            .collect()
+    };
+    let path_components = components(path);
+    let base_components = components(base);
+    let common_count = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    if base_components[common_count..]
+        .iter()
+        .any(|component| component == "..")
+    {
+        return path.to_owned();
+    }
+
+    let relative_components: Vec<_> = vec![".."; base_components.len() - common_count]
+        .into_iter()
+        .chain(path_components[common_count..].iter().map(String::as_str))
+        .collect();
+    if relative_components.is_empty() {
+        ".".to_owned()
+    } else {
+        relative_components.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path("/a/b/c.h", "/a"), "b/c.h");
+        assert_eq!(relative_path("/a/b/c.h", "/a/d/"), "../b/c.h");
+        assert_eq!(relative_path("/a", "/a"), ".");
+        assert_eq!(relative_path("c.h", "/a"), "c.h");
+    }
+
+    #[test]
+    fn test_relative_path_not_normalized() {
+        assert_eq!(relative_path("/a/b/c.h", "/a/d/../b"), "c.h");
+        assert_eq!(relative_path("/a/b/c.h", "/a/./d//"), "../b/c.h");
+        assert_eq!(relative_path("/a/b/../d/c.h", "/a/d"), "c.h");
+        assert_eq!(relative_path("/a/b/c.h", "/../a"), "b/c.h");
+        assert_eq!(relative_path("../a/c.h", "../b"), "../a/c.h");
+        assert_eq!(relative_path("a/c.h", "b/../a"), "c.h");
+        // The name of the directory above `a/c.h` is unknown.
+        assert_eq!(relative_path("a/c.h", "../b"), "a/c.h");
+    }
+
+    #[test]
+    fn test_strip_directory() {
+        assert_eq!(strip_directory("/a/b/c.h", "/a/b"), Some("/c.h"));
+        assert_eq!(strip_directory("/a/b/c.h", "/a/b/"), Some("/c.h"));
+        assert_eq!(strip_directory("/a/bc.h", "/a/b"), None);
+        assert_eq!(strip_directory("/a/b", "/a/b"), Some(""));
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    entries::entries,
+    entry::OwnedEntry,
+    error::{BucketError, Result},
+    hash::hash_key,
+    types::BucketCountType,
+    writer::{HeaderMapBuilder, HeaderMapWriteError},
+};
+
+/// Describes what got dropped when repairing a headermap.
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// The number of entries written to the repaired headermap.
+    pub recovered_entry_count: usize,
+    /// Buckets which could not be decoded.
+    pub bucket_errors: Vec<BucketError>,
+    /// Entries whose key matches the key of a recovered entry when
+    /// compared case-insensitively. Clang could never resolve to them.
+    pub duplicate_entries: Vec<OwnedEntry>,
+}
+
+/// Salvages every decodable entry from a possibly corrupt headermap and
+/// writes them into a fresh headermap with a recomputed bucket table and
+/// string section. Only fails if the header cannot be parsed.
+///
+/// If keys collide case-insensitively, the entry which Clang would have
+/// resolved to (i.e., the one closest to the start of the probe sequence)
+/// is kept.
+pub fn repair_headermap(bytes: &[u8]) -> Result<(Vec<u8>, RepairReport)> {
+    let entries_iterator = entries(bytes)?;
+    let bucket_mask = entries_iterator.header().header.bucket_count - 1;
+
+    let mut report = RepairReport::default();
+    let mut salvaged_entries = Vec::new();
+    for entry_result in entries_iterator {
+        match entry_result {
+            Ok((bucket_index, entry)) => salvaged_entries.push((bucket_index, entry)),
+            Err(bucket_error) => report.bucket_errors.push(bucket_error),
+        }
+    }
+
+    let probe_distance = |bucket_index: BucketCountType, key: &str| {
+        bucket_index.wrapping_sub(hash_key(key)) & bucket_mask
+    };
+    salvaged_entries.sort_by_key(|(bucket_index, entry)| probe_distance(*bucket_index, entry.key));
+
+    let mut builder = HeaderMapBuilder::new();
+    for (_, entry) in salvaged_entries {
+        match builder.add_entry(entry.key, entry.prefix, entry.suffix) {
+            Ok(()) => {}
+            Err(HeaderMapWriteError::DuplicateKey(_)) => {
+                report.duplicate_entries.push(entry.to_owned_entry())
+            }
+            Err(write_error) => return Err(write_error.into()),
+        }
+    }
+
+    report.recovered_entry_count = builder.len();
+    Ok((builder.build()?, report))
+}
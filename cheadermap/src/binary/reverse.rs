@@ -0,0 +1,105 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::HashMap;
+
+use crate::binary::entry::Entry;
+
+/// Maps target paths back to the entries which resolve to them, the
+/// equivalent of Clang's `HeaderMap::reverseLookupFilename()`. Unlike
+/// Clang, which only remembers the first key per target, all keys are
+/// kept, so every include spelling of a file can be found.
+///
+/// Target paths (`prefix` + `suffix`) are normalized lexically, see
+/// `normalize_path()`. Relative targets get resolved against the base
+/// directory, if there is one.
+#[derive(Debug, Default)]
+pub struct ReverseIndex<'a> {
+    entries_by_target: HashMap<String, Vec<Entry<'a>>>,
+}
+
+impl<'a> ReverseIndex<'a> {
+    pub fn new<I>(entries: I, base_directory: Option<&str>) -> ReverseIndex<'a>
+    where
+        I: IntoIterator<Item = Entry<'a>>,
+    {
+        let mut entries_by_target: HashMap<String, Vec<Entry<'a>>> = HashMap::new();
+        for entry in entries {
+            let target_path = format!("{}{}", entry.prefix, entry.suffix);
+            let target_path = match base_directory {
+                Some(base_directory) if !target_path.starts_with('/') => {
+                    format!("{}/{}", base_directory, target_path)
+                }
+                _ => target_path,
+            };
+            entries_by_target
+                .entry(normalize_path(&target_path))
+                .or_default()
+                .push(entry);
+        }
+        for entries in entries_by_target.values_mut() {
+            entries.sort();
+        }
+
+        ReverseIndex { entries_by_target }
+    }
+
+    /// Returns the entries resolving to `target_path`, sorted by key.
+    pub fn lookup(&self, target_path: &str) -> &[Entry<'a>] {
+        self.entries_by_target
+            .get(&normalize_path(target_path))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the number of distinct target paths.
+    pub fn len(&self) -> usize {
+        self.entries_by_target.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries_by_target.is_empty()
+    }
+}
+
+/// Normalizes `path` without accessing the file system: empty and `.`
+/// components are removed and `..` components remove the preceding one.
+/// Leading `..` components of relative paths are kept, while the ones of
+/// absolute paths are dropped (`/..` is `/`).
+pub fn normalize_path(path: &str) -> String {
+    let is_absolute = path.starts_with('/');
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => match components.last() {
+                Some(&last_component) if last_component != ".." => {
+                    components.pop();
+                }
+                _ if is_absolute => {}
+                _ => components.push(component),
+            },
+            component => components.push(component),
+        }
+    }
+
+    let normalized_path = components.join("/");
+    match (is_absolute, normalized_path.is_empty()) {
+        (true, _) => format!("/{}", normalized_path),
+        (false, true) => ".".to_owned(),
+        (false, false) => normalized_path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a//b/./c.h"), "/a/b/c.h");
+        assert_eq!(normalize_path("/a/b/../c.h"), "/a/c.h");
+        assert_eq!(normalize_path("/../a.h"), "/a.h");
+        assert_eq!(normalize_path("../a/../../b.h"), "../../b.h");
+        assert_eq!(normalize_path("a/.."), ".");
+        assert_eq!(normalize_path("/"), "/");
+    }
+}
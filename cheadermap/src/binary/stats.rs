@@ -0,0 +1,253 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::binary::{
+    bucket::Bucket,
+    entries::entries,
+    error::{BucketError, Result},
+    hash::hash_key,
+    header::{check_bucket_table_bounds, DataHeaderParseResult},
+    json,
+    types::{BucketCountType, StringSectionOffsetType},
+};
+
+/// The number of entries sharing a prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixStats {
+    pub prefix: String,
+    pub entry_count: usize,
+}
+
+/// Size and hash table health of a headermap.
+#[derive(Debug, Clone)]
+pub struct HeaderMapStats {
+    pub header: DataHeaderParseResult,
+    pub entry_count: usize,
+    /// Maps the number of buckets Clang probes to find an entry (1 if the
+    /// entry is in the bucket of its hash) to the number of such entries.
+    pub probe_length_histogram: BTreeMap<usize, usize>,
+    /// The longest run of occupied buckets. Looking up a missing key
+    /// probes up to one more bucket than that.
+    pub longest_cluster: usize,
+    /// The size of the string section, from its offset to the end of the file.
+    pub string_section_size: usize,
+    /// The size of the distinct strings referenced by buckets, including
+    /// their NULL terminating bytes.
+    pub referenced_string_bytes: usize,
+    /// The size the string section would need if every bucket had its own
+    /// copy of its key, prefix and suffix.
+    pub undeduplicated_string_bytes: usize,
+    /// Sorted by descending entry count, then by prefix.
+    pub prefixes: Vec<PrefixStats>,
+}
+
+impl HeaderMapStats {
+    pub fn bucket_count(&self) -> BucketCountType {
+        self.header.header.bucket_count
+    }
+
+    /// The ratio of occupied buckets.
+    pub fn load_factor(&self) -> f64 {
+        self.entry_count as f64 / self.bucket_count() as f64
+    }
+
+    /// The number of buckets probed by the most expensive successful lookup.
+    pub fn max_probe_length(&self) -> usize {
+        self.probe_length_histogram
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The number of bytes saved by sharing strings between buckets.
+    pub fn deduplication_savings(&self) -> usize {
+        self.undeduplicated_string_bytes
+            .saturating_sub(self.referenced_string_bytes)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+/// Collects the stats of a headermap. Returns an error if the header or
+/// any of the buckets are invalid.
+pub fn headermap_stats(bytes: &[u8]) -> Result<HeaderMapStats> {
+    let (stats, _) = collect_stats(bytes, true)?;
+    Ok(stats)
+}
+
+/// Collects the stats of a headermap, skipping any buckets which cannot
+/// be parsed. Skipped buckets still count as occupied when computing
+/// probe lengths, as Clang probes past them. Returns the stats and the
+/// errors of the skipped buckets. Fails if the header cannot be parsed
+/// or if the bucket table does not fit into `bytes`.
+pub fn headermap_stats_lenient(bytes: &[u8]) -> Result<(HeaderMapStats, Vec<BucketError>)> {
+    collect_stats(bytes, false)
+}
+
+fn collect_stats(
+    bytes: &[u8],
+    fail_on_bucket_error: bool,
+) -> Result<(HeaderMapStats, Vec<BucketError>)> {
+    let entries = entries(bytes)?;
+    let header = entries.header().clone();
+    check_bucket_table_bounds(&header.header, bytes)?;
+    let bucket_count = header.header.bucket_count as usize;
+
+    let mut bucket_errors = Vec::new();
+    let mut occupied_buckets = vec![false; bucket_count];
+    let mut probe_length_histogram = BTreeMap::new();
+    let mut referenced_offsets: HashSet<StringSectionOffsetType> = HashSet::new();
+    let mut referenced_string_bytes = 0;
+    let mut undeduplicated_string_bytes = 0;
+    let mut prefix_counts: HashMap<&str, usize> = HashMap::new();
+    for entry_result in entries {
+        let (bucket_index, entry) = match entry_result {
+            Ok(bucket_entry) => bucket_entry,
+            Err(bucket_error) if !fail_on_bucket_error => {
+                occupied_buckets[bucket_error.bucket_index as usize] = true;
+                bucket_errors.push(bucket_error);
+                continue;
+            }
+            Err(bucket_error) => return Err(bucket_error.into()),
+        };
+        let bucket = Bucket::new_at_index(bytes, bucket_index, header.swap_bytes)?
+            .expect("bucket is occupied");
+        occupied_buckets[bucket_index as usize] = true;
+
+        let home_index = hash_key(entry.key) as usize & (bucket_count - 1);
+        let probe_length = (bucket_index as usize + bucket_count - home_index) % bucket_count + 1;
+        *probe_length_histogram.entry(probe_length).or_insert(0) += 1;
+
+        for (offset, string) in [
+            (bucket.key_offset, entry.key),
+            (bucket.prefix_offset, entry.prefix),
+            (bucket.suffix_offset, entry.suffix),
+        ] {
+            let string_size = string.len() + 1;
+            undeduplicated_string_bytes += string_size;
+            if referenced_offsets.insert(offset) {
+                referenced_string_bytes += string_size;
+            }
+        }
+
+        *prefix_counts.entry(entry.prefix).or_insert(0) += 1;
+    }
+
+    let mut prefixes: Vec<_> = prefix_counts
+        .into_iter()
+        .map(|(prefix, entry_count)| PrefixStats {
+            prefix: prefix.to_owned(),
+            entry_count,
+        })
+        .collect();
+    prefixes.sort_by(|lhs, rhs| {
+        rhs.entry_count
+            .cmp(&lhs.entry_count)
+            .then_with(|| lhs.prefix.cmp(&rhs.prefix))
+    });
+
+    let stats = HeaderMapStats {
+        entry_count: probe_length_histogram.values().sum(),
+        probe_length_histogram,
+        longest_cluster: longest_cluster(&occupied_buckets),
+        string_section_size: bytes
+            .len()
+            .saturating_sub(header.header.string_section_offset as usize),
+        referenced_string_bytes,
+        undeduplicated_string_bytes,
+        prefixes,
+        header,
+    };
+    Ok((stats, bucket_errors))
+}
+
+/// Returns the longest run of occupied buckets, wrapping around the end
+/// of the bucket table like probing does.
+fn longest_cluster(occupied_buckets: &[bool]) -> usize {
+    let empty_index = match occupied_buckets.iter().position(|occupied| !occupied) {
+        Some(empty_index) => empty_index,
+        None => return occupied_buckets.len(),
+    };
+
+    let mut longest_cluster = 0;
+    let mut cluster = 0;
+    for offset in 1..=occupied_buckets.len() {
+        if occupied_buckets[(empty_index + offset) % occupied_buckets.len()] {
+            cluster += 1;
+            longest_cluster = longest_cluster.max(cluster);
+        } else {
+            cluster = 0;
+        }
+    }
+    longest_cluster
+}
+
+/// Prints the stats in the given format.
+pub fn print_stats<W>(writer: &mut W, stats: &HeaderMapStats, format: StatsFormat) -> Result<()>
+where
+    W: std::io::Write,
+{
+    match format {
+        StatsFormat::Text => print_text_stats(writer, stats),
+        StatsFormat::Json => json::print_json_stats(writer, stats, 4),
+    }
+}
+
+fn print_text_stats<W>(writer: &mut W, stats: &HeaderMapStats) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "Entries: {}", stats.entry_count)?;
+    writeln!(writer, "Buckets: {}", stats.bucket_count())?;
+    writeln!(writer, "Load factor: {:.2}", stats.load_factor())?;
+    writeln!(writer, "Probe lengths:")?;
+    for (probe_length, entry_count) in &stats.probe_length_histogram {
+        writeln!(writer, "  {}: {}", probe_length, entry_count)?;
+    }
+    writeln!(writer, "Max probe length: {}", stats.max_probe_length())?;
+    writeln!(writer, "Longest cluster: {}", stats.longest_cluster)?;
+    writeln!(
+        writer,
+        "String section size: {} bytes",
+        stats.string_section_size
+    )?;
+    writeln!(
+        writer,
+        "Referenced strings: {} bytes",
+        stats.referenced_string_bytes
+    )?;
+    writeln!(
+        writer,
+        "Saved by string deduplication: {} bytes",
+        stats.deduplication_savings()
+    )?;
+    writeln!(writer, "Prefixes: {}", stats.prefixes.len())?;
+    for prefix_stats in &stats.prefixes {
+        writeln!(
+            writer,
+            "  {}: {}",
+            prefix_stats.prefix, prefix_stats.entry_count
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_cluster() {
+        assert_eq!(longest_cluster(&[false, true, true, false]), 2);
+        assert_eq!(longest_cluster(&[true, false, true, true]), 3);
+        assert_eq!(longest_cluster(&[true, true]), 2);
+        assert_eq!(longest_cluster(&[false, false]), 0);
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    entry::Entry,
+    error::{BucketError, Result},
+    types::BucketCountType,
+};
+
+/// A bucket visited while looking up a key, see `HeaderMap::trace_lookup()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbedBucket<'a> {
+    pub bucket_index: BucketCountType,
+    pub state: ProbedBucketState<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbedBucketState<'a> {
+    /// The bucket is empty, which ends the lookup.
+    Empty,
+    /// The key of the entry matches case-insensitively, which ends the lookup.
+    Match(Entry<'a>),
+    /// The key of the entry differs, so probing continues with the next
+    /// bucket. `home_bucket_index` is the bucket the hash of the entry's
+    /// key points to, if it's the same as the looked up key's bucket,
+    /// the hashes collided, otherwise the entry got displaced by probing.
+    Mismatch {
+        entry: Entry<'a>,
+        home_bucket_index: BucketCountType,
+    },
+    /// The bucket cannot be parsed. `key` is `None` if the key cannot be
+    /// read, then Clang skips the bucket. If the key can be read, but the
+    /// prefix or suffix cannot, Clang skips the bucket unless `key_matches`,
+    /// in which case the key resolves to an empty path, i.e., a miss.
+    Corrupt {
+        key: Option<&'a str>,
+        key_matches: bool,
+        error: BucketError,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupOutcome<'a> {
+    Hit(Entry<'a>),
+    /// The probe chain ended at an empty bucket.
+    MissAtEmptyBucket(BucketCountType),
+    /// The probe chain ended at a corrupt bucket whose key matches.
+    MissAtCorruptBucket(BucketCountType),
+    /// Every bucket got probed without finding the key or an empty bucket.
+    /// Clang keeps probing in this case, i.e., it never finishes.
+    MissAfterWrapAround,
+}
+
+/// The steps of looking up a key the way Clang does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTrace<'a> {
+    pub key: String,
+    /// The hash of the key, see `hash_key()`.
+    pub hash: u32,
+    pub bucket_count: BucketCountType,
+    /// The bucket the hash points to, where probing starts.
+    pub start_bucket_index: BucketCountType,
+    /// The visited buckets, in probing order.
+    pub probes: Vec<ProbedBucket<'a>>,
+}
+
+impl<'a> LookupTrace<'a> {
+    pub fn outcome(&self) -> LookupOutcome<'a> {
+        match self
+            .probes
+            .last()
+            .map(|probe| (probe.bucket_index, &probe.state))
+        {
+            Some((_, ProbedBucketState::Match(entry))) => LookupOutcome::Hit(*entry),
+            Some((bucket_index, ProbedBucketState::Empty)) => {
+                LookupOutcome::MissAtEmptyBucket(bucket_index)
+            }
+            Some((
+                bucket_index,
+                ProbedBucketState::Corrupt {
+                    key_matches: true, ..
+                },
+            )) => LookupOutcome::MissAtCorruptBucket(bucket_index),
+            _ => LookupOutcome::MissAfterWrapAround,
+        }
+    }
+}
+
+/// Prints one line per probed bucket, followed by the outcome.
+pub fn print_lookup_trace<W>(writer: &mut W, trace: &LookupTrace) -> Result<()>
+where
+    W: std::io::Write,
+{
+    writeln!(writer, "Key: {}", trace.key)?;
+    writeln!(
+        writer,
+        "Hash: {} (of {:?})",
+        trace.hash,
+        trace.key.to_ascii_lowercase()
+    )?;
+    writeln!(
+        writer,
+        "Start bucket: {} ({} & {})",
+        trace.start_bucket_index,
+        trace.hash,
+        trace.bucket_count - 1
+    )?;
+
+    for (index, probe) in trace.probes.iter().enumerate() {
+        write!(
+            writer,
+            "Probe {}: bucket {}: ",
+            index + 1,
+            probe.bucket_index
+        )?;
+        match &probe.state {
+            ProbedBucketState::Empty => writeln!(writer, "empty")?,
+            ProbedBucketState::Match(entry) if entry.key == trace.key => {
+                writeln!(writer, "`{}` matches", entry.key)?
+            }
+            ProbedBucketState::Match(entry) => {
+                writeln!(writer, "`{}` matches case-insensitively", entry.key)?
+            }
+            ProbedBucketState::Mismatch {
+                entry,
+                home_bucket_index,
+            } if *home_bucket_index == trace.start_bucket_index => writeln!(
+                writer,
+                "`{}` does not match, its hash points to the same bucket",
+                entry.key
+            )?,
+            ProbedBucketState::Mismatch {
+                entry,
+                home_bucket_index,
+            } => writeln!(
+                writer,
+                "`{}` does not match, it got displaced from bucket {}",
+                entry.key, home_bucket_index
+            )?,
+            ProbedBucketState::Corrupt {
+                key: None, error, ..
+            } => writeln!(writer, "corrupt, Clang skips it: {}", error.kind)?,
+            ProbedBucketState::Corrupt {
+                key: Some(key),
+                key_matches: true,
+                error,
+            } => writeln!(
+                writer,
+                "`{}` matches, but its target cannot be read: {}",
+                key, error.kind
+            )?,
+            ProbedBucketState::Corrupt {
+                key: Some(key),
+                key_matches: false,
+                error,
+            } => writeln!(
+                writer,
+                "`{}` does not match, its target cannot be read: {}",
+                key, error.kind
+            )?,
+        }
+    }
+
+    match trace.outcome() {
+        LookupOutcome::Hit(entry) => writeln!(writer, "Hit: {}", entry)?,
+        LookupOutcome::MissAtEmptyBucket(bucket_index) => writeln!(
+            writer,
+            "Miss: bucket {} is empty, Clang falls back to the other search paths",
+            bucket_index
+        )?,
+        LookupOutcome::MissAtCorruptBucket(bucket_index) => writeln!(
+            writer,
+            "Miss: bucket {} resolves to an empty path, Clang falls back to the other search paths",
+            bucket_index
+        )?,
+        LookupOutcome::MissAfterWrapAround => writeln!(
+            writer,
+            "Miss: all {} buckets probed without finding an empty one, Clang would never finish the lookup",
+            trace.bucket_count
+        )?,
+    }
+
+    Ok(())
+}
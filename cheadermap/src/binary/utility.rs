@@ -56,3 +56,21 @@ impl DecodablePrimitive for u32 {
         Some(Self::from_ne_bytes(*x))
     }
 }
+
+/// Trait for primitive types which can be encoded into raw bytes.
+pub trait EncodablePrimitive: Sized {
+    /// Encodes without performing any endian adjustments (i.e., native byte order).
+    fn encode_to_bytes(self, bytes: &mut Vec<u8>);
+}
+
+impl EncodablePrimitive for u16 {
+    fn encode_to_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl EncodablePrimitive for u32 {
+    fn encode_to_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_ne_bytes());
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    bucket::{self, Bucket, BucketState},
+    entry::Entry,
+    error::BucketError,
+    hash::hash_key,
+    header::{
+        self, DataHeader, DataHeaderError, BUCKET_COUNT_FIELD_OFFSET,
+        MAX_VALUE_LENGTH_FIELD_OFFSET, STRING_COUNT_FIELD_OFFSET,
+        STRING_SECTION_OFFSET_FIELD_OFFSET,
+    },
+    types::*,
+    utility::Packable,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Clang can load the headermap, but it's inconsistent.
+    Warning,
+    /// Clang would reject the headermap or silently ignore entries.
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FindingKind {
+    #[error("Invalid data header: {0}")]
+    InvalidHeader(DataHeaderError),
+    #[error(
+        "Bucket table ends at `{bucket_table_end}`, past the end of the file (`{file_size}` bytes)"
+    )]
+    BucketTableOutOfBounds {
+        bucket_table_end: usize,
+        file_size: usize,
+    },
+    #[error("String section offset (`{string_section_offset}`) overlaps the bucket table, which ends at `{bucket_table_end}`")]
+    StringSectionOverlapsBucketTable {
+        string_section_offset: StringSectionOffsetType,
+        bucket_table_end: usize,
+    },
+    #[error(transparent)]
+    InvalidBucket(BucketError),
+    #[error("Bucket table has no empty buckets, Clang never terminates lookups for missing keys")]
+    NoEmptyBucket,
+    /// Probing stops at an empty bucket or at a corrupt bucket whose key
+    /// matches, which Clang resolves to an empty path.
+    #[error("Key `{key}` at bucket index `{bucket_index}` is unreachable, probing from bucket index `{start_bucket_index}` stops at bucket index `{stop_bucket_index}`")]
+    UnreachableEntry {
+        key: String,
+        bucket_index: BucketCountType,
+        start_bucket_index: BucketCountType,
+        stop_bucket_index: BucketCountType,
+    },
+    #[error("Key `{key}` at bucket index `{bucket_index}` is shadowed by key `{shadowing_key}` at bucket index `{shadowing_bucket_index}` (keys are case-insensitive)")]
+    DuplicateKey {
+        key: String,
+        bucket_index: BucketCountType,
+        shadowing_key: String,
+        shadowing_bucket_index: BucketCountType,
+    },
+    #[error("String count (`{string_count}`) does not match the number of entries (`{entry_count}`), expected one to three strings per entry")]
+    StringCountMismatch {
+        string_count: StringCountType,
+        entry_count: usize,
+    },
+    #[error("Max value length (`{max_value_length}`) does not match the longest value (`{longest_value_length}`)")]
+    MaxValueLengthMismatch {
+        max_value_length: MaxValueLength,
+        longest_value_length: usize,
+    },
+}
+
+/// A single problem found in a headermap, located at an absolute byte offset.
+#[derive(Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub offset: usize,
+    pub kind: FindingKind,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: [0x{:08X}] {}",
+            self.severity, self.offset, self.kind
+        )
+    }
+}
+
+fn bucket_offset(bucket_index: BucketCountType) -> usize {
+    DataHeader::packed_size() + (bucket_index as usize) * Bucket::packed_size()
+}
+
+/// Validates the structure of a headermap and checks whether Clang can
+/// find all of its entries. Returns the findings sorted by offset.
+///
+/// Note that `string_count` and `max_value_length` are not used by Clang
+/// and tools disagree on their values: Xcode counts strings before
+/// deduplicating them and always writes a zero `max_value_length`, while
+/// `HeaderMapBuilder` counts entries. So `string_count` is only reported
+/// if it's outside of one to three strings per entry (plus the empty
+/// string at the start of the string section) and a zero
+/// `max_value_length` is never reported.
+pub fn validate_headermap(bytes: &[u8]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let parse_result = match header::parse_header(bytes) {
+        Ok(parse_result) => parse_result,
+        Err(error) => {
+            findings.push(Finding {
+                severity: Severity::Error,
+                offset: 0,
+                kind: FindingKind::InvalidHeader(error),
+            });
+            return findings;
+        }
+    };
+    let header = &parse_result.header;
+
+    let bucket_table_end = header.bucket_table_end();
+    if (header.string_section_offset as usize) < bucket_table_end {
+        findings.push(Finding {
+            severity: Severity::Error,
+            offset: STRING_SECTION_OFFSET_FIELD_OFFSET,
+            kind: FindingKind::StringSectionOverlapsBucketTable {
+                string_section_offset: header.string_section_offset,
+                bucket_table_end,
+            },
+        });
+    }
+
+    // Clang rejects the whole headermap in this case, so there's no point
+    // in checking the individual entries.
+    if header::check_bucket_table_bounds(header, bytes).is_err() {
+        findings.push(Finding {
+            severity: Severity::Error,
+            offset: DataHeader::packed_size(),
+            kind: FindingKind::BucketTableOutOfBounds {
+                bucket_table_end,
+                file_size: bytes.len(),
+            },
+        });
+        findings.sort_by_key(|finding| finding.offset);
+        return findings;
+    }
+
+    let mut buckets = Vec::new();
+    for bucket_index in 0..header.bucket_count {
+        let bucket_state = bucket::parse_bucket_state_at_index(
+            bytes,
+            header,
+            bucket_index,
+            parse_result.swap_bytes,
+        );
+        if let BucketState::Corrupt { error, .. } = &bucket_state {
+            findings.push(Finding {
+                severity: Severity::Error,
+                offset: bucket_offset(bucket_index),
+                kind: FindingKind::InvalidBucket(error.clone()),
+            });
+        }
+        buckets.push(bucket_state);
+    }
+
+    if !buckets
+        .iter()
+        .any(|bucket_state| matches!(bucket_state, BucketState::Empty))
+    {
+        findings.push(Finding {
+            severity: Severity::Error,
+            offset: BUCKET_COUNT_FIELD_OFFSET,
+            kind: FindingKind::NoEmptyBucket,
+        });
+    }
+
+    let mut entry_count = 0;
+    let mut longest_value_length = 0;
+    for (bucket_index, bucket_state) in buckets.iter().enumerate() {
+        if let BucketState::Occupied(entry) = bucket_state {
+            entry_count += 1;
+            longest_value_length =
+                longest_value_length.max(entry.prefix.len() + entry.suffix.len());
+
+            let bucket_index = bucket_index as BucketCountType;
+            // Clang cannot find shadowed duplicate keys either.
+            if let Some(kind) = check_entry_reachable(&buckets, entry, bucket_index) {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    offset: bucket_offset(bucket_index),
+                    kind,
+                });
+            }
+        }
+    }
+
+    let string_count = header.string_count as usize;
+    if string_count < entry_count || string_count > 3 * entry_count + 1 {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            offset: STRING_COUNT_FIELD_OFFSET,
+            kind: FindingKind::StringCountMismatch {
+                string_count: header.string_count,
+                entry_count,
+            },
+        });
+    }
+
+    if header.max_value_length != 0 && (header.max_value_length as usize) != longest_value_length {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            offset: MAX_VALUE_LENGTH_FIELD_OFFSET,
+            kind: FindingKind::MaxValueLengthMismatch {
+                max_value_length: header.max_value_length,
+                longest_value_length,
+            },
+        });
+    }
+
+    findings.sort_by_key(|finding| finding.offset);
+    findings
+}
+
+/// Follows Clang's probe sequence for the key of `entry` and returns
+/// a finding if the probe does not end at `bucket_index`.
+fn check_entry_reachable(
+    buckets: &[BucketState],
+    entry: &Entry,
+    bucket_index: BucketCountType,
+) -> Option<FindingKind> {
+    let bucket_mask = (buckets.len() as BucketCountType) - 1;
+    let start_bucket_index = hash_key(entry.key) & bucket_mask;
+
+    let mut probe_index = start_bucket_index;
+    while probe_index != bucket_index {
+        match &buckets[probe_index as usize] {
+            BucketState::Empty => {
+                return Some(FindingKind::UnreachableEntry {
+                    key: entry.key.to_owned(),
+                    bucket_index,
+                    start_bucket_index,
+                    stop_bucket_index: probe_index,
+                });
+            }
+            BucketState::Corrupt {
+                key: Some(probed_key),
+                ..
+            } if probed_key.eq_ignore_ascii_case(entry.key) => {
+                return Some(FindingKind::UnreachableEntry {
+                    key: entry.key.to_owned(),
+                    bucket_index,
+                    start_bucket_index,
+                    stop_bucket_index: probe_index,
+                });
+            }
+            BucketState::Occupied(probed_entry)
+                if probed_entry.key.eq_ignore_ascii_case(entry.key) =>
+            {
+                return Some(FindingKind::DuplicateKey {
+                    key: entry.key.to_owned(),
+                    bucket_index,
+                    shadowing_key: probed_entry.key.to_owned(),
+                    shadowing_bucket_index: probe_index,
+                });
+            }
+            _ => {}
+        }
+        probe_index = (probe_index + 1) & bucket_mask;
+    }
+
+    None
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::{HashMap, HashSet};
+
+use crate::binary::{
+    bucket::Bucket,
+    byte_encoder::ByteEncoder,
+    entry::{Entry, OwnedEntry},
+    hash::hash_key,
+    header::{DataHeader, MAGIC_NATIVE_ENDIAN},
+    types::*,
+    utility::Packable,
+};
+
+/// The bucket table is kept at most this full (numerator, denominator),
+/// like LLVM's `hmaptool` does. Headermaps edited in place use the same
+/// limit, so their probe lengths match rebuilt ones.
+pub(crate) const MAX_LOAD_FACTOR: (usize, usize) = (1, 3);
+
+#[derive(thiserror::Error, Debug)]
+pub enum HeaderMapWriteError {
+    #[error("Duplicate key (keys are case-insensitive), found `{0}`")]
+    DuplicateKey(String),
+    #[error("String contains a NULL byte, found `{0}`")]
+    StringContainsNullByte(String),
+    #[error("Too many entries, found `{0}`")]
+    TooManyEntries(usize),
+    #[error("String section is too large, size `{0}`")]
+    StringSectionTooLarge(usize),
+    #[error("Value (prefix + suffix) is too long, length `{0}`")]
+    ValueTooLong(usize),
+}
+
+/// Builds a v1 headermap which can be loaded by Clang.
+///
+/// Entries are placed in a power-of-two bucket table using Clang's
+/// case-insensitive hash and linear probing. Strings are deduplicated
+/// in the string section. The output is written in native byte order.
+#[derive(Default)]
+pub struct HeaderMapBuilder {
+    entries: Vec<OwnedEntry>,
+    lowercase_keys: HashSet<String>,
+}
+
+/// Deduplicating accumulator for the string section. Offset 0 is
+/// `STRING_SECTION_OFFSET_RESERVED`, so the section starts with a NULL byte.
+struct StringSection {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, StringSectionOffsetType>,
+}
+
+impl StringSection {
+    fn new() -> StringSection {
+        StringSection {
+            bytes: vec![0x0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, string: &str) -> Result<StringSectionOffsetType, HeaderMapWriteError> {
+        if let Some(offset) = self.offsets.get(string) {
+            return Ok(*offset);
+        }
+
+        let offset = StringSectionOffsetType::try_from(self.bytes.len())
+            .map_err(|_| HeaderMapWriteError::StringSectionTooLarge(self.bytes.len()))?;
+        self.bytes.extend_from_slice(string.as_bytes());
+        self.bytes.push(0x0);
+        self.offsets.insert(string.to_owned(), offset);
+
+        Ok(offset)
+    }
+}
+
+impl HeaderMapBuilder {
+    pub fn new() -> HeaderMapBuilder {
+        HeaderMapBuilder::default()
+    }
+
+    /// Adds an entry mapping `key` -> `prefix` + `suffix`. Since Clang
+    /// matches keys case-insensitively, adding a key which differs from
+    /// an existing key only in case is an error.
+    pub fn add_entry(
+        &mut self,
+        key: &str,
+        prefix: &str,
+        suffix: &str,
+    ) -> Result<(), HeaderMapWriteError> {
+        for string in [key, prefix, suffix] {
+            if string.as_bytes().contains(&0x0) {
+                return Err(HeaderMapWriteError::StringContainsNullByte(
+                    string.to_owned(),
+                ));
+            }
+        }
+
+        if !self.lowercase_keys.insert(key.to_ascii_lowercase()) {
+            return Err(HeaderMapWriteError::DuplicateKey(key.to_owned()));
+        }
+
+        self.entries.push(OwnedEntry::new(key, prefix, suffix));
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of buckets for `entry_count` entries. The table
+    /// is kept at most `MAX_LOAD_FACTOR` full, which also guarantees an
+    /// empty bucket to terminate lookups.
+    fn bucket_count_for_entry_count(entry_count: usize) -> Option<BucketCountType> {
+        let (numerator, denominator) = MAX_LOAD_FACTOR;
+        let min_bucket_count = entry_count
+            .checked_mul(denominator)?
+            .checked_add(numerator - 1)?
+            / numerator;
+        let bucket_count = min_bucket_count.checked_next_power_of_two()?;
+        BucketCountType::try_from(bucket_count).ok()
+    }
+
+    /// Serializes the entries into the binary headermap format.
+    pub fn build(&self) -> Result<Vec<u8>, HeaderMapWriteError> {
+        let bucket_count = HeaderMapBuilder::bucket_count_for_entry_count(self.entries.len())
+            .ok_or(HeaderMapWriteError::TooManyEntries(self.entries.len()))?;
+        let bucket_mask = bucket_count - 1;
+
+        let mut buckets: Vec<Bucket> = (0..bucket_count).map(|_| Bucket::EMPTY).collect();
+        let mut string_section = StringSection::new();
+        let mut max_value_length = 0;
+
+        for entry in &self.entries {
+            let bucket = Bucket {
+                key_offset: string_section.insert(&entry.key)?,
+                prefix_offset: string_section.insert(&entry.prefix)?,
+                suffix_offset: string_section.insert(&entry.suffix)?,
+            };
+
+            let mut bucket_index = hash_key(&entry.key) & bucket_mask;
+            while buckets[bucket_index as usize].key_offset != STRING_SECTION_OFFSET_RESERVED {
+                bucket_index = (bucket_index + 1) & bucket_mask;
+            }
+            buckets[bucket_index as usize] = bucket;
+
+            let value_length = entry.prefix.len() + entry.suffix.len();
+            max_value_length = max_value_length.max(value_length);
+        }
+
+        let string_section_offset =
+            DataHeader::packed_size() + (bucket_count as usize) * Bucket::packed_size();
+        let string_section_end = string_section_offset + string_section.bytes.len();
+        if StringSectionOffsetType::try_from(string_section_end).is_err() {
+            return Err(HeaderMapWriteError::StringSectionTooLarge(
+                string_section.bytes.len(),
+            ));
+        }
+
+        let header = DataHeader {
+            magic: MAGIC_NATIVE_ENDIAN,
+            version: VERSION_1,
+            reserved: RESERVED,
+            string_section_offset: string_section_offset as StringSectionOffsetType,
+            // Clang's `HMapHeader` names this field `NumEntries`.
+            string_count: self.entries.len() as StringCountType,
+            bucket_count,
+            max_value_length: MaxValueLength::try_from(max_value_length)
+                .map_err(|_| HeaderMapWriteError::ValueTooLong(max_value_length))?,
+        };
+
+        let mut encoder = ByteEncoder::default();
+        header.encode(&mut encoder);
+        for bucket in &buckets {
+            bucket.encode(&mut encoder);
+        }
+        encoder.bytes.extend_from_slice(&string_section.bytes);
+
+        Ok(encoder.bytes)
+    }
+
+    /// Serializes the entries and writes them to `writer`.
+    pub fn write<W>(&self, writer: &mut W) -> crate::binary::Result<()>
+    where
+        W: std::io::Write,
+    {
+        let bytes = self.build()?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/// Builds a headermap from `entries`, see `HeaderMapBuilder`.
+pub fn build_headermap<'e, I>(entries: I) -> Result<Vec<u8>, HeaderMapWriteError>
+where
+    I: IntoIterator<Item = Entry<'e>>,
+{
+    let mut builder = HeaderMapBuilder::new();
+    for entry in entries {
+        builder.add_entry(entry.key, entry.prefix, entry.suffix)?;
+    }
+    builder.build()
+}
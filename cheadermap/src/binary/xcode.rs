@@ -0,0 +1,263 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use crate::binary::{
+    entry::OwnedEntry,
+    error::Result,
+    generate::GeneratedEntries,
+    json_value::{parse_json, JsonSyntaxError, JsonValue},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TargetDescriptionError {
+    #[error("Invalid JSON: {0}")]
+    Syntax(#[from] JsonSyntaxError),
+    #[error("Invalid or missing field `{0}`")]
+    InvalidField(String),
+    #[error("Invalid header visibility, expected `public`, `private` or `project`, found `{0}`")]
+    InvalidVisibility(String),
+}
+
+/// The visibility of a header in the headers build phase of a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderVisibility {
+    Public,
+    Private,
+    Project,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetHeader {
+    pub path: String,
+    pub visibility: HeaderVisibility,
+}
+
+impl TargetHeader {
+    /// Returns `true` if the header gets installed into the product.
+    fn is_product_header(&self) -> bool {
+        self.visibility != HeaderVisibility::Project
+    }
+}
+
+/// Describes a target and its headers, see `parse_target_descriptions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetDescription {
+    pub name: String,
+    /// The name headers get namespaced by, e.g., the framework name.
+    pub product_name: String,
+    pub framework: bool,
+    pub headers: Vec<TargetHeader>,
+}
+
+/// The hmap flavours which Xcode generates for each target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcodeHeaderMapKind {
+    /// Every header of every target, keyed by `Header.h`.
+    ProjectHeaders,
+    /// Every header of the target, keyed by `Header.h`. Public and private
+    /// headers of frameworks are also keyed by `Product/Header.h`.
+    OwnTargetHeaders,
+    /// Public and private headers of every target, keyed by `Product/Header.h`.
+    AllTargetHeaders,
+    /// Every header of every non-framework target, keyed by `Header.h`.
+    AllNonFrameworkTargetHeaders,
+    /// Public and private headers of every framework target (i.e., the
+    /// headers installed into the products), keyed by `Product/Header.h`.
+    AllProductHeaders,
+}
+
+impl XcodeHeaderMapKind {
+    pub const ALL: [XcodeHeaderMapKind; 5] = [
+        XcodeHeaderMapKind::ProjectHeaders,
+        XcodeHeaderMapKind::OwnTargetHeaders,
+        XcodeHeaderMapKind::AllTargetHeaders,
+        XcodeHeaderMapKind::AllNonFrameworkTargetHeaders,
+        XcodeHeaderMapKind::AllProductHeaders,
+    ];
+
+    /// The suffix of the hmap file name, as used by Xcode.
+    pub fn name(&self) -> &'static str {
+        match self {
+            XcodeHeaderMapKind::ProjectHeaders => "project-headers",
+            XcodeHeaderMapKind::OwnTargetHeaders => "own-target-headers",
+            XcodeHeaderMapKind::AllTargetHeaders => "all-target-headers",
+            XcodeHeaderMapKind::AllNonFrameworkTargetHeaders => "all-non-framework-target-headers",
+            XcodeHeaderMapKind::AllProductHeaders => "all-product-headers",
+        }
+    }
+}
+
+/// A generated hmap of a target.
+#[derive(Debug)]
+pub struct XcodeHeaderMap {
+    pub target_name: String,
+    pub kind: XcodeHeaderMapKind,
+    pub entries: GeneratedEntries,
+}
+
+impl XcodeHeaderMap {
+    /// Returns the file name Xcode uses, i.e., `<Target>-<kind>.hmap`.
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.hmap", self.target_name, self.kind.name())
+    }
+}
+
+/// Parses target descriptions from JSON in the following shape, where
+/// `product_name` defaults to `name`, `framework` defaults to `false`
+/// and `visibility` defaults to `project`:
+///
+/// ```json
+/// {
+///     "targets": [
+///         {
+///             "name": "MyFramework",
+///             "product_name": "MyFramework",
+///             "framework": true,
+///             "headers": [
+///                 {"path": "/path/to/Header.h", "visibility": "public"}
+///             ]
+///         }
+///     ]
+/// }
+/// ```
+pub fn parse_target_descriptions(bytes: &[u8]) -> Result<Vec<TargetDescription>> {
+    let value = parse_json(bytes).map_err(TargetDescriptionError::from)?;
+    let targets = value
+        .get("targets")
+        .and_then(|targets| targets.as_array())
+        .ok_or_else(|| TargetDescriptionError::InvalidField("targets".to_owned()))?;
+
+    let mut target_descriptions = Vec::new();
+    for target in targets {
+        let name = get_string_field(target, "name")?
+            .ok_or_else(|| TargetDescriptionError::InvalidField("name".to_owned()))?;
+        let product_name = get_string_field(target, "product_name")?.unwrap_or(name);
+        let framework = match target.get("framework") {
+            None => false,
+            Some(framework) => framework
+                .as_bool()
+                .ok_or_else(|| TargetDescriptionError::InvalidField("framework".to_owned()))?,
+        };
+
+        let headers_value = match target.get("headers") {
+            None => &[][..],
+            Some(headers) => headers
+                .as_array()
+                .ok_or_else(|| TargetDescriptionError::InvalidField("headers".to_owned()))?,
+        };
+        let mut headers = Vec::new();
+        for header in headers_value {
+            let path = get_string_field(header, "path")?
+                .ok_or_else(|| TargetDescriptionError::InvalidField("path".to_owned()))?;
+            let visibility = match get_string_field(header, "visibility")? {
+                None | Some("project") => HeaderVisibility::Project,
+                Some("public") => HeaderVisibility::Public,
+                Some("private") => HeaderVisibility::Private,
+                Some(visibility) => {
+                    return Err(
+                        TargetDescriptionError::InvalidVisibility(visibility.to_owned()).into(),
+                    )
+                }
+            };
+            headers.push(TargetHeader {
+                path: path.to_owned(),
+                visibility,
+            });
+        }
+
+        target_descriptions.push(TargetDescription {
+            name: name.to_owned(),
+            product_name: product_name.to_owned(),
+            framework,
+            headers,
+        });
+    }
+
+    Ok(target_descriptions)
+}
+
+/// Returns the string value of `field`, if present.
+fn get_string_field<'v>(
+    value: &'v JsonValue,
+    field: &str,
+) -> std::result::Result<Option<&'v str>, TargetDescriptionError> {
+    match value.get(field) {
+        None => Ok(None),
+        Some(field_value) => field_value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| TargetDescriptionError::InvalidField(field.to_owned())),
+    }
+}
+
+/// Generates the hmap of the given kind for `targets[target_index]`.
+/// Headers of the target itself come first, then the headers of the other
+/// targets in the order given. If keys collide (case-insensitively, like
+/// Clang), the first header wins.
+///
+/// # Panics
+///
+/// Panics if `target_index` is out of bounds.
+pub fn generate_xcode_headermap(
+    targets: &[TargetDescription],
+    target_index: usize,
+    kind: XcodeHeaderMapKind,
+) -> XcodeHeaderMap {
+    let own_target = &targets[target_index];
+    let ordered_targets = std::iter::once(own_target).chain(
+        targets
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != target_index)
+            .map(|(_, target)| target),
+    );
+
+    let mut entries = GeneratedEntries::default();
+    for target in ordered_targets {
+        let is_own_target = std::ptr::eq(target, own_target);
+        for header in &target.headers {
+            let (flat_key, namespaced_key) = match kind {
+                XcodeHeaderMapKind::ProjectHeaders => (true, false),
+                XcodeHeaderMapKind::OwnTargetHeaders => (
+                    is_own_target,
+                    is_own_target && target.framework && header.is_product_header(),
+                ),
+                XcodeHeaderMapKind::AllTargetHeaders => (false, header.is_product_header()),
+                XcodeHeaderMapKind::AllNonFrameworkTargetHeaders => (!target.framework, false),
+                XcodeHeaderMapKind::AllProductHeaders => {
+                    (false, target.framework && header.is_product_header())
+                }
+            };
+
+            let target_entry = OwnedEntry::from_target_path("", &header.path);
+            if flat_key {
+                entries.add_entry(OwnedEntry {
+                    key: target_entry.suffix.clone(),
+                    ..target_entry.clone()
+                });
+            }
+            if namespaced_key {
+                entries.add_entry(OwnedEntry {
+                    key: format!("{}/{}", target.product_name, target_entry.suffix),
+                    ..target_entry
+                });
+            }
+        }
+    }
+
+    XcodeHeaderMap {
+        target_name: own_target.name.clone(),
+        kind,
+        entries,
+    }
+}
+
+/// Generates every kind of hmap for every target.
+pub fn generate_xcode_headermaps(targets: &[TargetDescription]) -> Vec<XcodeHeaderMap> {
+    (0..targets.len())
+        .flat_map(|target_index| {
+            XcodeHeaderMapKind::ALL
+                .iter()
+                .map(move |kind| generate_xcode_headermap(targets, target_index, *kind))
+        })
+        .collect()
+}
@@ -1,3 +1,5 @@
 // Copyright (c) 2022 Milen Dzhumerov
 
 pub mod binary;
+
+pub use binary::{Error, Result};
@@ -0,0 +1,167 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{
+    diff_entries, parse_headermap, parse_headermap_lenient, print_diff, ChangedEntry, DiffFormat,
+    Entry, OwnedEntry,
+};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_diff_with_itself() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let diff = diff_entries(&entries, &entries);
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn test_diff() {
+    let old_entries = [
+        Entry {
+            key: "Removed.h",
+            prefix: "/a/",
+            suffix: "Removed.h",
+        },
+        Entry {
+            key: "Changed.h",
+            prefix: "/a/",
+            suffix: "Changed.h",
+        },
+        Entry {
+            key: "Same.h",
+            prefix: "/a/",
+            suffix: "Same.h",
+        },
+    ];
+    let new_entries = [
+        Entry {
+            key: "Same.h",
+            prefix: "/a/",
+            suffix: "Same.h",
+        },
+        Entry {
+            key: "Changed.h",
+            prefix: "/b/",
+            suffix: "Changed.h",
+        },
+        Entry {
+            key: "Added.h",
+            prefix: "/a/",
+            suffix: "Added.h",
+        },
+    ];
+
+    let diff = diff_entries(&old_entries, &new_entries);
+    assert!(!diff.is_empty());
+    assert_eq!(diff.added, vec![OwnedEntry::from(new_entries[2])]);
+    assert_eq!(diff.removed, vec![OwnedEntry::from(old_entries[0])]);
+    assert_eq!(
+        diff.changed,
+        vec![ChangedEntry {
+            old: OwnedEntry::from(old_entries[1]),
+            new: OwnedEntry::from(new_entries[1]),
+        }]
+    );
+    assert!(diff.changed[0].prefix_changed());
+    assert!(!diff.changed[0].suffix_changed());
+
+    let mut text_output = Vec::new();
+    print_diff(&mut text_output, &diff, DiffFormat::Text).unwrap();
+    assert_eq!(
+        String::from_utf8(text_output).unwrap(),
+        "- Removed.h -> /a/Removed.h\n\
+         + Added.h -> /a/Added.h\n\
+         ~ Changed.h -> /a/Changed.h => /b/Changed.h (prefix changed)\n"
+    );
+
+    let mut json_output = Vec::new();
+    print_diff(&mut json_output, &diff, DiffFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json_output).unwrap();
+    assert_eq!(json["added"]["Added.h"]["prefix"], "/a/");
+    assert_eq!(json["removed"]["Removed.h"]["suffix"], "Removed.h");
+    assert_eq!(json["changed"]["Changed.h"]["new"]["prefix"], "/b/");
+    assert_eq!(json["changed"]["Changed.h"]["prefix_changed"], true);
+    assert_eq!(json["changed"]["Changed.h"]["suffix_changed"], false);
+}
+
+#[test]
+fn test_diff_with_empty_headermap() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let diff = diff_entries(&[], &entries);
+    assert_eq!(diff.added.len(), entries.len());
+    assert!(diff.removed.is_empty() && diff.changed.is_empty());
+    assert!(diff.added.windows(2).all(|pair| pair[0].key < pair[1].key));
+
+    let diff = diff_entries(&entries, &[]);
+    assert_eq!(diff.removed.len(), entries.len());
+    assert!(diff.added.is_empty() && diff.changed.is_empty());
+
+    let diff = diff_entries(&[], &[]);
+    assert!(diff.is_empty());
+    let mut text_output = Vec::new();
+    print_diff(&mut text_output, &diff, DiffFormat::Text).unwrap();
+    assert!(text_output.is_empty());
+    let mut json_output = Vec::new();
+    print_diff(&mut json_output, &diff, DiffFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json_output).unwrap();
+    for section in ["added", "removed", "changed"] {
+        assert!(json[section].as_object().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn test_diff_salvaged_entries() {
+    let intact_bytes = test_data::read_sdwebimage_binary_hmap();
+    let intact_entries = parse_headermap(&intact_bytes, true).unwrap();
+    let cut_off_bytes = test_data::read_sdwebimage_cut_off_binary_hmap();
+    let (salvaged_entries, bucket_errors) = parse_headermap_lenient(&cut_off_bytes).unwrap();
+
+    let diff = diff_entries(&intact_entries, &salvaged_entries);
+    assert_eq!(diff.removed.len(), bucket_errors.len());
+    assert!(diff.added.is_empty() && diff.changed.is_empty());
+}
+
+#[test]
+fn test_diff_key_case_and_target_split() {
+    let old_entries = [
+        Entry {
+            key: "Foo/Bar.h",
+            prefix: "/a/",
+            suffix: "Bar.h",
+        },
+        Entry {
+            key: "Split.h",
+            prefix: "/a/",
+            suffix: "b/Split.h",
+        },
+    ];
+    let new_entries = [
+        Entry {
+            key: "foo/bar.h",
+            prefix: "/a/",
+            suffix: "Bar.h",
+        },
+        Entry {
+            key: "Split.h",
+            prefix: "/a/b/",
+            suffix: "Split.h",
+        },
+    ];
+
+    // Keys are compared exactly, even though Clang would find both.
+    let diff = diff_entries(&old_entries, &new_entries);
+    assert_eq!(diff.removed, vec![OwnedEntry::from(old_entries[0])]);
+    assert_eq!(diff.added, vec![OwnedEntry::from(new_entries[0])]);
+
+    // The same target split differently is a change of the layout.
+    assert_eq!(diff.changed.len(), 1);
+    assert!(diff.changed[0].prefix_changed() && diff.changed[0].suffix_changed());
+    assert_eq!(
+        diff.changed[0].old.target_path(),
+        diff.changed[0].new.target_path()
+    );
+}
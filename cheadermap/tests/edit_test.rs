@@ -0,0 +1,246 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{
+    edit_headermap, parse_edit_script, validate_headermap, DataHeaderError, EditError,
+    EditOperation, EditOutcome, HeaderMap, HeaderMapBuilder, OwnedEntry,
+};
+
+mod test_data;
+
+use test_data::BucketField;
+
+#[test]
+fn test_sdwebimage_edit_in_place() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let original_headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+
+    let operations = parse_edit_script(
+        b"# Keys are case-insensitive\n\
+          rm sdwebimage/sdwebimage.h\n\
+          set New.h /x/y/New.h\n\
+          mv SDWebImage/SDImageCache.h SDImageCache.h\n\
+          replace SDWebImage/SDWebImageManager.h /Users/milen/Desktop/SDWebImage/SDWebImage/Core/SDWebImageManager.h\n",
+    )
+    .unwrap();
+    let (edited_bytes, outcome) = edit_headermap(&bytes, &operations).unwrap();
+    assert_eq!(outcome, EditOutcome::InPlace);
+    assert_eq!(edited_bytes.len(), bytes.len() + "New.h\0/x/y/\0".len());
+    assert!(validate_headermap(&edited_bytes).is_empty());
+
+    let headermap = HeaderMap::from_bytes(&edited_bytes[..]).unwrap();
+    assert_eq!(headermap.len(), original_headermap.len());
+    assert!(headermap.get("SDWebImage/SDWebImage.h").is_none());
+    assert!(headermap.get("SDWebImage/SDImageCache.h").is_none());
+    let entry = headermap.lookup("new.h").unwrap();
+    assert_eq!((entry.prefix, entry.suffix), ("/x/y/", "New.h"));
+    assert_eq!(
+        headermap.get("SDImageCache.h").unwrap().target_path(),
+        original_headermap
+            .get("SDWebImage/SDImageCache.h")
+            .unwrap()
+            .target_path()
+    );
+
+    // All other entries are still reachable after removing from a cluster.
+    for entry in original_headermap.iter() {
+        if entry.key != "SDWebImage/SDWebImage.h" && entry.key != "SDWebImage/SDImageCache.h" {
+            assert_eq!(headermap.get(entry.key), Some(entry));
+        }
+    }
+}
+
+#[test]
+fn test_edit_load_factor_matches_builder() {
+    // 5 entries get 16 buckets, which can hold 5 entries at a load of 1/3.
+    let mut builder = HeaderMapBuilder::new();
+    for index in 0..5 {
+        builder
+            .add_entry(&format!("K{}.h", index), "/k/", &format!("K{}.h", index))
+            .unwrap();
+    }
+    let bytes = builder.build().unwrap();
+    assert_eq!(bytes[16..20], 16u32.to_ne_bytes());
+
+    let remove = EditOperation::Remove("K0.h".to_owned());
+    let add = EditOperation::Add(OwnedEntry::new("New.h", "/x/", "New.h"));
+    let (_, outcome) = edit_headermap(&bytes, &[remove, add.clone()]).unwrap();
+    assert_eq!(outcome, EditOutcome::InPlace);
+
+    // A sixth entry makes the builder pick 32 buckets, so does editing.
+    let (edited_bytes, outcome) = edit_headermap(&bytes, &[add]).unwrap();
+    assert_eq!(outcome, EditOutcome::Rebuilt);
+    assert_eq!(edited_bytes[16..20], 32u32.to_ne_bytes());
+}
+
+#[test]
+fn test_edit_rebuilds_when_full() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("A.h", "/a/", "A.h").unwrap();
+    let bytes = builder.build().unwrap();
+    let bucket_count = HeaderMap::from_bytes(&bytes[..])
+        .unwrap()
+        .header()
+        .header
+        .bucket_count;
+
+    let operations: Vec<_> = (0..bucket_count)
+        .map(|index| {
+            EditOperation::Add(OwnedEntry::from_target_path(
+                &format!("K{}.h", index),
+                &format!("/k/K{}.h", index),
+            ))
+        })
+        .collect();
+    let (edited_bytes, outcome) = edit_headermap(&bytes, &operations).unwrap();
+    assert_eq!(outcome, EditOutcome::Rebuilt);
+    let headermap = HeaderMap::from_bytes(&edited_bytes[..]).unwrap();
+    assert_eq!(headermap.len(), bucket_count as usize + 1);
+    assert!(headermap.header().header.bucket_count > bucket_count);
+    assert!(headermap.lookup("A.H").is_some());
+
+    match edit_headermap(
+        &bytes,
+        &[EditOperation::Add(OwnedEntry::new("a.h", "/b/", "a.h"))],
+    ) {
+        Err(cheadermap::Error::Edit(EditError::KeyExists(key))) => assert_eq!(key, "a.h"),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    match edit_headermap(&bytes, &[EditOperation::Remove("B.h".to_owned())]) {
+        Err(cheadermap::Error::Edit(EditError::KeyNotFound(key))) => assert_eq!(key, "B.h"),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    match parse_edit_script(b"add A.h\n") {
+        Err(cheadermap::Error::Edit(EditError::InvalidScriptLine(1, _))) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_parse_edit_script_whitespace() {
+    let operations = parse_edit_script(b"add  New.h \t /x/y/New.h\nrm\t\tOld.h\n").unwrap();
+    assert_eq!(
+        operations,
+        vec![
+            EditOperation::Add(OwnedEntry::new("New.h", "/x/y/", "New.h")),
+            EditOperation::Remove("Old.h".to_owned()),
+        ]
+    );
+
+    match parse_edit_script(b"rm\n") {
+        Err(cheadermap::Error::Edit(EditError::InvalidScriptLine(1, _))) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_edit_truncated_bucket_table() {
+    let bytes = test_data::get_truncated_bucket_table_hmap_bytes(1 << 31);
+    match edit_headermap(&bytes, &[EditOperation::Remove("A.h".to_owned())]) {
+        Err(cheadermap::Error::Header(DataHeaderError::BucketTableOutOfBounds(bucket_count))) => {
+            assert_eq!(bucket_count, 1 << 31)
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_edit_updates_header_counts() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("A.h", "/a/", "A.h").unwrap();
+    builder.add_entry("B.h", "/long/path/", "B.h").unwrap();
+    let bytes = builder.build().unwrap();
+
+    // Removing the longest value and most entries in place must not leave
+    // overstated header counts behind.
+    let (edited_bytes, outcome) =
+        edit_headermap(&bytes, &[EditOperation::Remove("B.h".to_owned())]).unwrap();
+    assert_eq!(outcome, EditOutcome::InPlace);
+    assert!(validate_headermap(&edited_bytes).is_empty());
+    assert_eq!(edited_bytes[20..24], ("/a/A.h".len() as u32).to_ne_bytes());
+}
+
+#[test]
+fn test_edit_full_bucket_table() {
+    let keys = test_data::COLLIDING_KEYS;
+    let bytes = test_data::build_full_colliding_keys_hmap_bytes();
+
+    // Removing from a table without empty buckets keeps the rest reachable.
+    let (edited_bytes, outcome) =
+        edit_headermap(&bytes, &[EditOperation::Remove("AB.h".to_owned())]).unwrap();
+    assert_eq!(outcome, EditOutcome::InPlace);
+    let headermap = HeaderMap::from_bytes(&edited_bytes[..]).unwrap();
+    assert_eq!(headermap.len(), 3);
+    assert!(headermap.lookup("ab.h").is_none());
+    for key in &keys[1..] {
+        assert_eq!(headermap.lookup(key).unwrap().key, *key);
+    }
+
+    let add = EditOperation::Add(OwnedEntry::new("New.h", "/x/", "New.h"));
+    let (edited_bytes, outcome) = edit_headermap(&bytes, &[add]).unwrap();
+    assert_eq!(outcome, EditOutcome::Rebuilt);
+    assert!(validate_headermap(&edited_bytes).is_empty());
+    assert_eq!(HeaderMap::from_bytes(edited_bytes).unwrap().len(), 5);
+}
+
+#[test]
+fn test_edit_empty_headermap() {
+    let bytes = test_data::build_hmap_bytes(&[]);
+    let add = EditOperation::Add(OwnedEntry::new("A.h", "/a/", "A.h"));
+    let (edited_bytes, _) = edit_headermap(&bytes, &[add]).unwrap();
+    assert!(validate_headermap(&edited_bytes).is_empty());
+    assert_eq!(
+        HeaderMap::from_bytes(&edited_bytes[..])
+            .unwrap()
+            .lookup("a.h")
+            .unwrap()
+            .prefix,
+        "/a/"
+    );
+
+    // Removing the last entry leaves a valid, empty headermap.
+    let (edited_bytes, outcome) =
+        edit_headermap(&edited_bytes, &[EditOperation::Remove("A.h".to_owned())]).unwrap();
+    assert_eq!(outcome, EditOutcome::InPlace);
+    assert!(validate_headermap(&edited_bytes).is_empty());
+    assert!(HeaderMap::from_bytes(edited_bytes).unwrap().is_empty());
+}
+
+#[test]
+fn test_edit_colliding_keys() {
+    let bytes = test_data::build_hmap_bytes(&[("ab.h", "/1/ab.h"), ("ba.h", "/2/ba.h")]);
+
+    // Renaming to a key which only differs in case keeps the entry.
+    let rename = EditOperation::Rename {
+        from: "ab.h".to_owned(),
+        to: "AB.h".to_owned(),
+    };
+    let (edited_bytes, _) = edit_headermap(&bytes, &[rename]).unwrap();
+    let headermap = HeaderMap::from_bytes(&edited_bytes[..]).unwrap();
+    assert!(headermap.get("ab.h").is_none());
+    assert_eq!(headermap.get("AB.h").unwrap().prefix, "/1/");
+    assert_eq!(headermap.lookup("ba.h").unwrap().prefix, "/2/");
+
+    // Renaming onto the colliding key fails and changes nothing.
+    let rename = EditOperation::Rename {
+        from: "ab.h".to_owned(),
+        to: "BA.H".to_owned(),
+    };
+    match edit_headermap(&bytes, &[rename]) {
+        Err(cheadermap::Error::Edit(EditError::KeyExists(key))) => assert_eq!(key, "BA.H"),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_edit_corrupt_bucket() {
+    let mut bytes = test_data::build_hmap_bytes(&[("A.h", "/a/A.h"), ("B.h", "/b/B.h")]);
+    let bucket_index = test_data::bucket_index_of_key(&bytes, "A.h");
+    test_data::corrupt_bucket(&mut bytes, bucket_index, BucketField::Suffix);
+
+    match edit_headermap(&bytes, &[EditOperation::Remove("B.h".to_owned())]) {
+        Err(cheadermap::Error::Bucket(bucket_error)) => {
+            assert_eq!(bucket_error.bucket_index, bucket_index)
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
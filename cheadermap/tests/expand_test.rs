@@ -0,0 +1,41 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::{collections::HashMap, path::PathBuf};
+
+use cheadermap::binary::{expand_entries, parse_headermap, ExpandError, HeaderMapBuilder};
+
+#[test]
+fn test_expand_template_headermap() {
+    let mut builder = HeaderMapBuilder::new();
+    builder
+        .add_entry("Foo/Foo.h", "$(SRCROOT)/Foo/", "Foo.h")
+        .unwrap();
+    builder
+        .add_entry("Bar.h", "${BUILD_ROOT}/gen/", "Bar.h")
+        .unwrap();
+    let template_bytes = builder.build().unwrap();
+    let entries = parse_headermap(&template_bytes, true).unwrap();
+
+    let mut variables = HashMap::from([("SRCROOT".to_owned(), "/src".to_owned())]);
+    match expand_entries(&entries, &variables) {
+        Err(cheadermap::Error::Expand(ExpandError::UndefinedVariables(names))) => {
+            assert_eq!(names, vec!["BUILD_ROOT".to_owned()])
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    variables.insert("BUILD_ROOT".to_owned(), "/build".to_owned());
+    let mut expanded_entries = expand_entries(&entries, &variables).unwrap();
+    expanded_entries.sort();
+    let target_paths: Vec<_> = expanded_entries
+        .iter()
+        .map(|entry| entry.target_path())
+        .collect();
+    assert_eq!(
+        target_paths,
+        [
+            PathBuf::from("/build/gen/Bar.h"),
+            PathBuf::from("/src/Foo/Foo.h")
+        ]
+    );
+}
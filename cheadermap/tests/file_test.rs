@@ -3,10 +3,16 @@
 use core::panic;
 use std::fs;
 
-use cheadermap::{self, binary::print_headermap};
+use cheadermap::{
+    self,
+    binary::{print_headermap, print_lookup_results, OutputFormat},
+};
 use serde_json::{Map, Value};
 
-use cheadermap::binary::{parse_headermap, Entry};
+use cheadermap::binary::{
+    parse_headermap, BucketErrorKind, DataHeaderError, Entry, HeaderMap, HeaderMapBuilder,
+    OwnedEntry, StringError,
+};
 
 mod test_data;
 
@@ -95,3 +101,317 @@ fn test_single_entry_enumeration() {
 
     assert_eq!(item_count, 1);
 }
+
+#[test]
+fn test_sdwebimage_lookup() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+    let headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+
+    for entry in parse_headermap(&bytes, true).unwrap() {
+        let found_entry = headermap.lookup(entry.key).unwrap();
+        assert_eq!(found_entry, entry);
+
+        let uppercase_key = entry.key.to_ascii_uppercase();
+        let found_entry = headermap.lookup(&uppercase_key).unwrap();
+        assert_eq!(found_entry, entry);
+        assert!(headermap.get(&uppercase_key).is_none());
+    }
+
+    assert!(headermap.lookup("SDWebImage/Missing.h").is_none());
+}
+
+#[test]
+fn test_print_lookup_results() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let headermap = HeaderMap::open(binary_hmap_path).unwrap();
+    let results: Vec<_> = [
+        "sdwebimage/sdweakproxy.h",
+        "Missing.h",
+        "SDWebImage/SDWeakProxy.h",
+    ]
+    .into_iter()
+    .map(|key| (key, headermap.lookup(key)))
+    .collect();
+    let target_path = "/Users/milen/Desktop/SDWebImage/SDWebImage/Private/SDWeakProxy.h";
+
+    let mut output = Vec::new();
+    print_lookup_results(&mut output, &results, OutputFormat::Text).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        format!(
+            "sdwebimage/sdweakproxy.h -> {}\nMissing.h: not found\nSDWebImage/SDWeakProxy.h -> {}\n",
+            target_path, target_path
+        )
+    );
+
+    let mut output = Vec::new();
+    print_lookup_results(&mut output, &results, OutputFormat::JsonFlat).unwrap();
+    let json_value: Value = serde_json::from_slice(&output).unwrap();
+    let json_object = json_value.as_object().unwrap();
+    assert_eq!(
+        json_object.keys().collect::<Vec<_>>(),
+        [
+            "Missing.h",
+            "SDWebImage/SDWeakProxy.h",
+            "sdwebimage/sdweakproxy.h"
+        ]
+    );
+    assert_eq!(json_object["sdwebimage/sdweakproxy.h"], target_path);
+    assert!(json_object["Missing.h"].is_null());
+}
+
+#[test]
+fn test_reserved_prefix_offset() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("Foo.h", "/a/", "Foo.h").unwrap();
+    let mut bytes = builder.build().unwrap();
+    let bucket_index = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .0 as usize;
+
+    // Like in Clang, only a reserved key offset makes a bucket empty. The
+    // reserved prefix offset points to the empty string at offset 0.
+    let prefix_offset = 24 + bucket_index * 12 + 4;
+    bytes[prefix_offset..prefix_offset + 4].copy_from_slice(&0u32.to_ne_bytes());
+    let headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+    assert_eq!(headermap.len(), 1);
+    let entry = headermap.lookup("foo.h").unwrap();
+    assert_eq!((entry.prefix, entry.suffix), ("", "Foo.h"));
+}
+
+#[test]
+fn test_lookup_corrupt_buckets() {
+    // Anagrams have the same hash, so `ba.h` is probed after `ab.h`.
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("ab.h", "/a/", "ab.h").unwrap();
+    builder.add_entry("ba.h", "/b/", "ba.h").unwrap();
+    let bytes = builder.build().unwrap();
+    let bucket_index = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .map(Result::unwrap)
+        .find(|(_, entry)| entry.key == "ab.h")
+        .unwrap()
+        .0 as usize;
+    let bucket_offset = 24 + bucket_index * 12;
+
+    // An unreadable key gets skipped.
+    let mut corrupt_key_bytes = bytes.clone();
+    corrupt_key_bytes[bucket_offset..bucket_offset + 4].copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    assert!(HeaderMap::from_bytes(&corrupt_key_bytes[..]).is_err());
+    let headermap = HeaderMap::from_bytes_lenient(&corrupt_key_bytes[..]).unwrap();
+    assert_eq!(headermap.len(), 1);
+    let bucket_errors = headermap.bucket_errors();
+    assert_eq!(bucket_errors.len(), 1);
+    assert_eq!(bucket_errors[0].bucket_index as usize, bucket_index);
+    assert_eq!(headermap.lookup("BA.H").unwrap().prefix, "/b/");
+
+    // A matching key with an unreadable target ends the lookup.
+    let mut corrupt_suffix_bytes = bytes;
+    corrupt_suffix_bytes[bucket_offset + 8..bucket_offset + 12]
+        .copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    let headermap = HeaderMap::from_bytes_lenient(&corrupt_suffix_bytes[..]).unwrap();
+    assert!(headermap.lookup("ab.h").is_none());
+    assert_eq!(headermap.lookup("ba.h").unwrap().prefix, "/b/");
+}
+
+#[test]
+fn test_zero_bucket_count() {
+    // Lookups mask bucket indices with `bucket_count - 1`, which relies on
+    // the header parser rejecting a bucket count of zero.
+    let bytes = test_data::get_truncated_bucket_table_hmap_bytes(0);
+    for result in [
+        HeaderMap::from_bytes(&bytes[..]),
+        HeaderMap::from_bytes_lenient(&bytes[..]),
+    ] {
+        assert!(matches!(
+            result,
+            Err(cheadermap::Error::Header(
+                DataHeaderError::BucketCountNotPowerOfTwo(0)
+            ))
+        ));
+    }
+}
+
+#[test]
+fn test_sdwebimage_owned_headermap() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let headermap = HeaderMap::open(binary_hmap_path).unwrap();
+    assert_eq!(headermap.len(), 75);
+    assert!(!headermap.header().swap_bytes);
+
+    let mut entries: Vec<OwnedEntry> = headermap.iter().map(OwnedEntry::from).collect();
+    assert_eq!(entries.len(), headermap.len());
+    entries.sort();
+
+    let text_output: String = entries.iter().map(|entry| format!("{}\n", entry)).collect();
+    let expected_text_output =
+        fs::read_to_string(test_data::get_sdwebimage_binary_reference_text_output()).unwrap();
+    assert_eq!(text_output, expected_text_output);
+
+    let entry = headermap.get("SDWebImage/SDWeakProxy.h").unwrap();
+    assert_eq!(
+        entry.target_path(),
+        std::path::Path::new("/Users/milen/Desktop/SDWebImage/SDWebImage/Private/SDWeakProxy.h")
+    );
+}
+
+#[test]
+fn test_malformed_sdwebimage_headermaps() {
+    for hmap_path in test_data::get_sdwebimage_malformed_binary_hmap_paths() {
+        assert!(HeaderMap::open(hmap_path).is_err());
+    }
+}
+
+#[test]
+fn test_malformed_sdwebimage_errors() {
+    let header_only_path = test_data::get_path_for_test_resources_filename(
+        "SDWebImage-all-target-headers.malformed.header-only.hmap",
+    );
+    let bytes = fs::read(header_only_path).unwrap();
+    assert!(matches!(
+        parse_headermap(&bytes, true),
+        Err(cheadermap::Error::Header(
+            DataHeaderError::StringSectionOffsetOutOfBounds(3096)
+        ))
+    ));
+
+    let cut_off_path = test_data::get_path_for_test_resources_filename(
+        "SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap",
+    );
+    let bytes = fs::read(cut_off_path).unwrap();
+    match parse_headermap(&bytes, true) {
+        Err(cheadermap::Error::Bucket(bucket_error)) => {
+            assert!(bucket_error.offset >= bytes.len());
+            assert!(matches!(
+                bucket_error.kind,
+                BucketErrorKind::InvalidString(StringError::OutOfBounds)
+            ));
+        }
+        result => panic!("Expected bucket error, found: {:#?}", result),
+    }
+}
+
+#[test]
+fn test_entries_iterator() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+
+    let bucket_indices: Vec<_> = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .map(|entry_result| entry_result.unwrap().0)
+        .collect();
+    assert_eq!(bucket_indices.len(), 75);
+    assert!(bucket_indices.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let private_entry_count = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|(_, entry)| entry.prefix.ends_with("/Private/"))
+        .count();
+    assert!(private_entry_count > 0 && private_entry_count < 75);
+}
+
+#[test]
+fn test_entries_iterator_bucket_errors() {
+    let cut_off_path = test_data::get_path_for_test_resources_filename(
+        "SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap",
+    );
+    let bytes = fs::read(cut_off_path).unwrap();
+
+    let (entries, bucket_errors): (Vec<_>, Vec<_>) = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .partition(Result::is_ok);
+    assert!(!entries.is_empty());
+    assert!(!bucket_errors.is_empty());
+    assert_eq!(entries.len() + bucket_errors.len(), 75);
+
+    let first_bucket_error = bucket_errors[0].as_ref().unwrap_err();
+    let entries_before_error = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .take_while(Result::is_ok)
+        .count();
+    let expected_entries_before_error = entries
+        .iter()
+        .filter(|entry| entry.as_ref().unwrap().0 < first_bucket_error.bucket_index)
+        .count();
+    assert_eq!(entries_before_error, expected_entries_before_error);
+}
+
+#[test]
+fn test_entries_iterator_truncated_bucket_table() {
+    let bytes = test_data::get_truncated_bucket_table_hmap_bytes(1 << 26);
+
+    // Only the first bucket past the end is reported, not all 2^26.
+    let bucket_errors: Vec<_> = cheadermap::binary::entries(&bytes)
+        .unwrap()
+        .map(Result::unwrap_err)
+        .collect();
+    assert_eq!(bucket_errors.len(), 1);
+    assert_eq!(bucket_errors[0].bucket_index, 0);
+    assert!(matches!(
+        bucket_errors[0].kind,
+        BucketErrorKind::OffsetParseError
+    ));
+
+    let (entries, bucket_errors) = cheadermap::binary::parse_headermap_lenient(&bytes).unwrap();
+    assert!(entries.is_empty());
+    assert_eq!(bucket_errors.len(), 1);
+}
+
+#[test]
+fn test_lenient_parsing() {
+    let cut_off_path = test_data::get_path_for_test_resources_filename(
+        "SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap",
+    );
+    let bytes = fs::read(cut_off_path).unwrap();
+    let (entries, bucket_errors) = cheadermap::binary::parse_headermap_lenient(&bytes).unwrap();
+    assert_eq!(entries.len(), 32);
+    assert_eq!(bucket_errors.len(), 43);
+
+    // All recovered entries must match the intact headermap
+    let intact_bytes = fs::read(test_data::get_sdwebimage_binary_hmap_path()).unwrap();
+    let intact_headermap = HeaderMap::from_bytes(&intact_bytes[..]).unwrap();
+    for entry in entries {
+        assert_eq!(intact_headermap.get(entry.key), Some(entry));
+    }
+}
+
+#[test]
+fn test_sdwebimage_reference_json_print() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let reference_json_bytes =
+        fs::read(test_data::get_sdwebimage_binary_reference_json_output()).unwrap();
+    let reference_json_value: Value = serde_json::from_slice(&reference_json_bytes).unwrap();
+
+    let mut output_buffer = Vec::new();
+    print_headermap(
+        &mut output_buffer,
+        &binary_hmap_path,
+        cheadermap::binary::OutputFormat::Json,
+    )
+    .unwrap();
+    let json_value: Value = serde_json::from_slice(&output_buffer).unwrap();
+    assert_eq!(json_value, reference_json_value);
+
+    let bytes = fs::read(&binary_hmap_path).unwrap();
+    let header = cheadermap::binary::parse_header(&bytes).unwrap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+    let mut output_buffer = Vec::new();
+    cheadermap::binary::print_entries_with_header(
+        &mut output_buffer,
+        &entries,
+        Some(&header),
+        cheadermap::binary::OutputFormat::JsonFlat,
+    )
+    .unwrap();
+    let json_value: Value = serde_json::from_slice(&output_buffer).unwrap();
+    assert_eq!(json_value["header"]["bucket_count"], 256);
+    for entry in &entries {
+        let target = json_value["entries"][entry.key].as_str().unwrap();
+        assert_eq!(target, format!("{}{}", entry.prefix, entry.suffix));
+    }
+}
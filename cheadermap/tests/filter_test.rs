@@ -0,0 +1,95 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{parse_headermap, parse_key_list, Entry, EntryFilter};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_filter() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let mut filter = EntryFilter::new();
+    filter.add_key_glob("SDWebImageMapKit/*").unwrap();
+    let filtered_entries = filter.filter(&entries);
+    assert_eq!(filtered_entries.len(), 2);
+    assert!(filtered_entries
+        .iter()
+        .all(|entry| entry.key.starts_with("SDWebImageMapKit/")));
+
+    let mut filter = EntryFilter::new();
+    filter.add_target_glob("**/Private/**").unwrap();
+    let private_entries = filter.filter(&entries);
+    filter.set_invert(true);
+    let public_entries = filter.filter(&entries);
+    assert_eq!(private_entries.len(), 14);
+    assert_eq!(private_entries.len() + public_entries.len(), entries.len());
+
+    let mut filter = EntryFilter::new();
+    filter.add_keys(parse_key_list(
+        b"# Keys are case-insensitive\nsdwebimage/sdwebimage.h\nSDWebImage/Missing.h\n",
+    ));
+    filter.add_target_glob("/Users/**/SDWebImage.h").unwrap();
+    let filtered_entries = filter.filter(&entries);
+    assert_eq!(filtered_entries.len(), 1);
+    assert_eq!(filtered_entries[0].key, "SDWebImage/SDWebImage.h");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_sdwebimage_filter_regex() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let mut filter = EntryFilter::new();
+    filter.add_target_regex("/Private/").unwrap();
+    assert_eq!(filter.filter(&entries).len(), 14);
+
+    assert!(EntryFilter::new().add_key_regex("(").is_err());
+}
+
+fn entry<'a>(key: &'a str, prefix: &'a str, suffix: &'a str) -> Entry<'a> {
+    Entry {
+        key,
+        prefix,
+        suffix,
+    }
+}
+
+#[test]
+fn test_filter_without_criteria() {
+    let entries = [entry("A.h", "/a/", "A.h")];
+
+    let mut filter = EntryFilter::new();
+    assert_eq!(filter.filter(&entries), entries);
+    assert!(filter.filter(&[]).is_empty());
+    filter.set_invert(true);
+    assert!(filter.filter(&entries).is_empty());
+
+    // An empty key list lists no key, so nothing matches.
+    let mut filter = EntryFilter::new();
+    filter.add_keys(parse_key_list(b"# Nothing to see\n\n"));
+    assert!(filter.filter(&entries).is_empty());
+}
+
+#[test]
+fn test_filter_case_and_non_ascii() {
+    let entries = [
+        entry("Lib/Café.h", "/src/", "Café.h"),
+        entry("lib/Other.h", "/src/", "Other.h"),
+    ];
+
+    // Globs are case-sensitive, the key list is not.
+    let mut filter = EntryFilter::new();
+    filter.add_key_glob("Lib/*").unwrap();
+    assert_eq!(filter.filter(&entries), vec![entries[0]]);
+
+    let mut filter = EntryFilter::new();
+    filter.add_keys(["LIB/OTHER.H"]);
+    assert_eq!(filter.filter(&entries), vec![entries[1]]);
+
+    // `?` matches a character, not a byte.
+    let mut filter = EntryFilter::new();
+    filter.add_target_glob("/src/Caf?.h").unwrap();
+    assert_eq!(filter.filter(&entries), vec![entries[0]]);
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{generate_headermap_entries, GenerateOptions, OwnedEntry};
+
+mod test_data;
+
+#[test]
+fn test_generate_from_directories() {
+    let root = test_data::create_temp_dir("generate");
+    let include_dir = root.join("include");
+    let sources_dir = root.join("Sources");
+    fs::create_dir_all(include_dir.join("nested")).unwrap();
+    fs::create_dir_all(&sources_dir).unwrap();
+    for path in [
+        include_dir.join("Header.h"),
+        include_dir.join("nested/Nested.hpp"),
+        include_dir.join("Source.c"),
+        sources_dir.join("header.h"),
+        sources_dir.join("Table.inc"),
+    ] {
+        fs::write(path, "").unwrap();
+    }
+
+    let options = GenerateOptions {
+        namespace: Some("Namespace".to_owned()),
+        ..Default::default()
+    };
+    let generated_entries = generate_headermap_entries(&[&include_dir, &sources_dir], &options);
+    let root = fs::canonicalize(&root).unwrap();
+    let _ = fs::remove_dir_all(&root);
+    let generated_entries = generated_entries.unwrap();
+
+    let include_prefix = format!("{}/include/", root.display());
+    let nested_prefix = format!("{}/include/nested/", root.display());
+    let sources_prefix = format!("{}/Sources/", root.display());
+    assert_eq!(
+        generated_entries.entries,
+        vec![
+            OwnedEntry::new("Header.h", &include_prefix, "Header.h"),
+            OwnedEntry::new("Namespace/Header.h", &include_prefix, "Header.h"),
+            OwnedEntry::new("Nested.hpp", &nested_prefix, "Nested.hpp"),
+            OwnedEntry::new("Namespace/Nested.hpp", &nested_prefix, "Nested.hpp"),
+            OwnedEntry::new("Table.inc", &sources_prefix, "Table.inc"),
+            OwnedEntry::new("Namespace/Table.inc", &sources_prefix, "Table.inc"),
+        ]
+    );
+    assert_eq!(
+        generated_entries.duplicate_entries,
+        vec![
+            OwnedEntry::new("header.h", &sources_prefix, "header.h"),
+            OwnedEntry::new("Namespace/header.h", &sources_prefix, "header.h"),
+        ]
+    );
+}
+
+#[test]
+fn test_generate_default_namespace() {
+    let root = test_data::create_temp_dir("generate-namespace");
+    let include_dir = root.join("MyLib");
+    fs::create_dir_all(&include_dir).unwrap();
+    for path in [include_dir.join("Upper.H"), include_dir.join("Other.HPP")] {
+        fs::write(path, "").unwrap();
+    }
+
+    let generated_entries = generate_headermap_entries(&[&include_dir], &Default::default());
+    let root = fs::canonicalize(&root).unwrap();
+    let _ = fs::remove_dir_all(&root);
+    let generated_entries = generated_entries.unwrap();
+
+    let prefix = format!("{}/MyLib/", root.display());
+    assert_eq!(
+        generated_entries.entries,
+        vec![
+            OwnedEntry::new("Other.HPP", &prefix, "Other.HPP"),
+            OwnedEntry::new("MyLib/Other.HPP", &prefix, "Other.HPP"),
+            OwnedEntry::new("Upper.H", &prefix, "Upper.H"),
+            OwnedEntry::new("MyLib/Upper.H", &prefix, "Upper.H"),
+        ]
+    );
+}
+
+#[test]
+fn test_generate_from_empty_and_missing_directories() {
+    let root = test_data::create_temp_dir("generate-empty");
+    let empty_dir = root.join("Empty");
+    fs::create_dir_all(empty_dir.join("nested")).unwrap();
+    fs::write(empty_dir.join("README"), "").unwrap();
+    fs::write(empty_dir.join("nested/.h"), "").unwrap();
+
+    let generated_entries = generate_headermap_entries(&[&empty_dir], &Default::default());
+    let missing_dir_result =
+        generate_headermap_entries(&[root.join("Missing")], &Default::default());
+    let _ = fs::remove_dir_all(&root);
+
+    let generated_entries = generated_entries.unwrap();
+    assert!(generated_entries.entries.is_empty());
+    assert!(generated_entries.duplicate_entries.is_empty());
+    assert!(matches!(missing_dir_result, Err(cheadermap::Error::Io(_))));
+}
+
+#[test]
+fn test_generate_same_file_name_in_default_namespaces() {
+    let root = test_data::create_temp_dir("generate-collisions");
+    let first_dir = root.join("First");
+    let second_dir = root.join("Second");
+    fs::create_dir_all(&first_dir).unwrap();
+    fs::create_dir_all(&second_dir).unwrap();
+    fs::write(first_dir.join("Common.h"), "").unwrap();
+    fs::write(second_dir.join("COMMON.h"), "").unwrap();
+
+    let generated_entries =
+        generate_headermap_entries(&[&first_dir, &second_dir], &Default::default());
+    let root = fs::canonicalize(&root).unwrap();
+    let _ = fs::remove_dir_all(&root);
+    let generated_entries = generated_entries.unwrap();
+
+    // Only the file name keys collide, each namespace keeps its header.
+    let first_prefix = format!("{}/First/", root.display());
+    let second_prefix = format!("{}/Second/", root.display());
+    assert_eq!(
+        generated_entries.entries,
+        vec![
+            OwnedEntry::new("Common.h", &first_prefix, "Common.h"),
+            OwnedEntry::new("First/Common.h", &first_prefix, "Common.h"),
+            OwnedEntry::new("Second/COMMON.h", &second_prefix, "COMMON.h"),
+        ]
+    );
+    assert_eq!(
+        generated_entries.duplicate_entries,
+        vec![OwnedEntry::new("COMMON.h", &second_prefix, "COMMON.h")]
+    );
+}
+
+#[test]
+fn test_generate_custom_extensions() {
+    let root = test_data::create_temp_dir("generate-extensions");
+    let include_dir = root.join("Lib");
+    fs::create_dir_all(&include_dir).unwrap();
+    for file_name in ["A.h", "B.H", "C.def", "D.hpp"] {
+        fs::write(include_dir.join(file_name), "").unwrap();
+    }
+
+    let options = GenerateOptions {
+        extensions: vec!["h".to_owned(), "DEF".to_owned()],
+        ..Default::default()
+    };
+    let generated_entries = generate_headermap_entries(&[&include_dir], &options);
+    let no_extensions = GenerateOptions {
+        extensions: Vec::new(),
+        ..Default::default()
+    };
+    let no_entries = generate_headermap_entries(&[&include_dir], &no_extensions);
+    let _ = fs::remove_dir_all(&root);
+
+    let keys: Vec<_> = generated_entries
+        .unwrap()
+        .entries
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["A.h", "Lib/A.h", "B.H", "Lib/B.H", "C.def", "Lib/C.def"]
+    );
+    assert!(no_entries.unwrap().entries.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_generate_does_not_follow_directory_symlinks() {
+    let root = test_data::create_temp_dir("generate-symlinks");
+    let include_dir = root.join("Lib");
+    fs::create_dir_all(&include_dir).unwrap();
+    fs::write(include_dir.join("A.h"), "").unwrap();
+    fs::write(root.join("Linked.h"), "").unwrap();
+    std::os::unix::fs::symlink(&include_dir, include_dir.join("Cycle")).unwrap();
+    std::os::unix::fs::symlink(root.join("Linked.h"), include_dir.join("Linked.h")).unwrap();
+
+    let generated_entries = generate_headermap_entries(&[&include_dir], &Default::default());
+    let _ = fs::remove_dir_all(&root);
+
+    // The cycle is not entered, symlinked headers are mapped.
+    let keys: Vec<_> = generated_entries
+        .unwrap()
+        .entries
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+    assert_eq!(keys, vec!["A.h", "Lib/A.h", "Linked.h", "Lib/Linked.h"]);
+}
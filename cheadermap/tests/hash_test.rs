@@ -0,0 +1,44 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{bucket_index_for_key, entries, hash_key, parse_header};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_hash() {
+    // The hmap got written by Xcode, so the buckets reflect Clang's hash.
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+    let bucket_count = parse_header(&bytes).unwrap().header.bucket_count;
+
+    let mut occupied_buckets = vec![false; bucket_count as usize];
+    let mut entries_by_bucket = Vec::new();
+    for entry_result in entries(&bytes).unwrap() {
+        let (bucket_index, entry) = entry_result.unwrap();
+        occupied_buckets[bucket_index as usize] = true;
+        entries_by_bucket.push((bucket_index, entry));
+    }
+
+    // Each entry is either in the bucket of its hash or was displaced
+    // by linear probing, so all buckets in between are occupied.
+    let mut home_bucket_count = 0;
+    for (bucket_index, entry) in entries_by_bucket {
+        let mut probe_index = bucket_index_for_key(entry.key, bucket_count);
+        if probe_index == bucket_index {
+            home_bucket_count += 1;
+        }
+        while probe_index != bucket_index {
+            assert!(occupied_buckets[probe_index as usize]);
+            probe_index = (probe_index + 1) % bucket_count;
+        }
+    }
+    assert_eq!(home_bucket_count, 62);
+
+    assert_eq!(hash_key("SDWebImage/SDWeakProxy.h"), 32006);
+    assert_eq!(
+        bucket_index_for_key("SDWebImage/SDWeakProxy.h", bucket_count),
+        6
+    );
+}
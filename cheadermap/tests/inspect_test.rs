@@ -0,0 +1,135 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{
+    edit_headermap, inspect_headermap, print_layout, EditOperation, HeaderMapBuilder,
+};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_inspect() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+    let layout = inspect_headermap(&bytes).unwrap();
+
+    assert!(!layout.swap_bytes);
+    let field = layout.header_field("string_section_offset").unwrap();
+    assert_eq!((field.offset, field.size, field.value), (8, 4, 3096));
+    assert_eq!(layout.header_field("version").unwrap().size, 2);
+    assert_eq!(layout.buckets.len(), 256);
+    assert_eq!(
+        layout
+            .buckets
+            .iter()
+            .filter(|bucket| !bucket.is_empty())
+            .count(),
+        75
+    );
+    let bucket = &layout.buckets[1];
+    assert_eq!(bucket.offset, 24 + 12);
+    assert_eq!(bucket.prefix.field_offset, 24 + 12 + 4);
+    assert_eq!(
+        bucket.key.string.as_ref().unwrap().as_ref().unwrap(),
+        &"SDWebImage/UIImage+Transform.h"
+    );
+    assert!(layout.buckets[2].key.is_reserved());
+    assert!(layout.unused_string_ranges.is_empty());
+
+    // Removing an entry in place orphans its strings.
+    let (edited_bytes, _) = edit_headermap(
+        &bytes,
+        &[EditOperation::Remove("SDWebImage/SDWebImage.h".to_owned())],
+    )
+    .unwrap();
+    let layout = inspect_headermap(&edited_bytes).unwrap();
+    let unused_bytes: Vec<_> = layout
+        .unused_string_ranges
+        .iter()
+        .map(|range| &edited_bytes[range.clone()])
+        .collect();
+    assert_eq!(
+        unused_bytes,
+        vec![b"SDWebImage/SDWebImage.h\0SDWebImage.h\0"]
+    );
+
+    let mut output = Vec::new();
+    print_layout(&mut output, &layout, &edited_bytes).unwrap();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("(37 bytes): \"SDWebImage/SDWebImage.h\\0SDWebImage.h\\0\""));
+}
+
+#[test]
+fn test_inspect_malformed_headermap() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let mut bytes = fs::read(binary_hmap_path).unwrap();
+
+    // Clang rejects a bucket count which is not a power of two and the
+    // bucket table does not fit into the file anymore.
+    bytes[16..20].copy_from_slice(&1000u32.to_ne_bytes());
+    bytes.truncate(1000);
+    let layout = inspect_headermap(&bytes).unwrap();
+    assert_eq!(layout.header_field("bucket_count").unwrap().value, 1000);
+    assert_eq!(layout.buckets.len(), (1000 - 24) / 12);
+    assert!(layout.unused_string_ranges.is_empty());
+
+    assert!(inspect_headermap(&bytes[..20]).is_err());
+}
+
+#[test]
+fn test_inspect_reserved_offsets() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("A.h", "/a/", "A.h").unwrap();
+    builder.add_entry("B.h", "/b/", "B.h").unwrap();
+    let mut bytes = builder.build().unwrap();
+    let bucket_offset = |bytes: &[u8], key: &str| {
+        let layout = inspect_headermap(bytes).unwrap();
+        let bucket = layout
+            .buckets
+            .iter()
+            .find(|bucket| matches!(bucket.key.string, Some(Ok(string)) if string == key))
+            .unwrap();
+        bucket.offset
+    };
+
+    // Like in Clang, a reserved prefix offset points to the empty string at
+    // the start of the string section, the bucket is not empty.
+    let a_offset = bucket_offset(&bytes, "A.h");
+    bytes[a_offset + 4..a_offset + 8].copy_from_slice(&0u32.to_ne_bytes());
+    // Only the reserved key offset makes a bucket empty.
+    let b_offset = bucket_offset(&bytes, "B.h");
+    bytes[b_offset..b_offset + 4].copy_from_slice(&0u32.to_ne_bytes());
+
+    let layout = inspect_headermap(&bytes).unwrap();
+    let a_bucket = &layout.buckets[(a_offset - 24) / 12];
+    assert!(!a_bucket.is_empty());
+    assert!(a_bucket.prefix.is_reserved());
+    assert!(matches!(a_bucket.prefix.string, Some(Ok(""))));
+    let b_bucket = &layout.buckets[(b_offset - 24) / 12];
+    assert!(b_bucket.is_empty());
+    assert!(!b_bucket.prefix.is_reserved());
+    assert!(b_bucket.prefix.string.is_none());
+    assert_eq!(
+        layout
+            .buckets
+            .iter()
+            .filter(|bucket| !bucket.is_empty())
+            .count(),
+        1
+    );
+
+    let mut output = Vec::new();
+    print_layout(&mut output, &layout, &bytes).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(&format!(
+        "#{}: empty (key offset reserved), ignored prefix {} and suffix {}",
+        b_bucket.index, b_bucket.prefix.value, b_bucket.suffix.value
+    )));
+    assert!(output.contains(&format!(
+        "0x{:08x} prefix 0 -> \"\"",
+        a_bucket.prefix.field_offset
+    )));
+    assert!(!output.contains("reserved\n"));
+}
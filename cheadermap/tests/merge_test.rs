@@ -0,0 +1,181 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{
+    build_headermap, merge_entries, parse_headermap, parse_headermap_lenient, validate_headermap,
+    Entry, MergeError, MergePolicy, OwnedEntry, SourcedEntry,
+};
+
+mod test_data;
+
+fn entry<'a>(key: &'a str, prefix: &'a str, suffix: &'a str) -> Entry<'a> {
+    Entry {
+        key,
+        prefix,
+        suffix,
+    }
+}
+
+fn sourced_entry(input_index: usize, entry: Entry) -> SourcedEntry {
+    SourcedEntry {
+        input_index,
+        entry: entry.to_owned_entry(),
+    }
+}
+
+#[test]
+fn test_merge_sdwebimage_with_itself() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let inputs = vec![entries.clone(), entries.clone()];
+    let merge_result = merge_entries(&inputs, MergePolicy::CaseInsensitiveError).unwrap();
+    assert!(merge_result.conflicts.is_empty());
+    assert_eq!(merge_result.entries.len(), entries.len());
+    assert!(merge_result
+        .entries
+        .iter()
+        .all(|sourced_entry| sourced_entry.input_index == 0));
+}
+
+#[test]
+fn test_merge_policies() {
+    let first = entry("Foo/Bar.h", "/a/", "Bar.h");
+    let second = entry("Foo/Bar.h", "/b/", "Bar.h");
+    let inputs = vec![vec![first, entry("A.h", "/a/", "A.h")], vec![second]];
+
+    let merge_result = merge_entries(&inputs, MergePolicy::FirstWins).unwrap();
+    assert_eq!(
+        merge_result.entries,
+        vec![
+            sourced_entry(0, first),
+            sourced_entry(0, entry("A.h", "/a/", "A.h"))
+        ]
+    );
+    assert_eq!(merge_result.conflicts.len(), 1);
+    assert_eq!(merge_result.conflicts[0].winner, sourced_entry(0, first));
+    assert_eq!(
+        merge_result.conflicts[0].losers,
+        vec![sourced_entry(1, second)]
+    );
+
+    let merge_result = merge_entries(&inputs, MergePolicy::LastWins).unwrap();
+    assert_eq!(merge_result.entries[0], sourced_entry(1, second));
+    assert_eq!(
+        merge_result.conflicts[0].losers,
+        vec![sourced_entry(0, first)]
+    );
+
+    let merge_error = merge_entries(&inputs, MergePolicy::Error).unwrap_err();
+    assert!(matches!(
+        merge_error,
+        cheadermap::Error::Merge(MergeError::Conflict { .. })
+    ));
+}
+
+#[test]
+fn test_merge_case_insensitive_keys() {
+    let first = entry("Foo/Bar.h", "/a/", "Bar.h");
+    let second = entry("foo/bar.h", "/b/", "bar.h");
+    let same_path = entry("FOO/BAR.H", "/a/Bar", ".h");
+    let inputs = vec![vec![first], vec![second, same_path]];
+
+    let merge_result = merge_entries(&inputs, MergePolicy::Error).unwrap();
+    assert_eq!(merge_result.entries, vec![sourced_entry(0, first)]);
+    assert_eq!(
+        merge_result.conflicts[0].losers,
+        vec![sourced_entry(1, second)]
+    );
+
+    let merge_error = merge_entries(&inputs, MergePolicy::CaseInsensitiveError).unwrap_err();
+    match merge_error {
+        cheadermap::Error::Merge(MergeError::Conflict {
+            existing,
+            conflicting,
+        }) => {
+            assert_eq!(existing.entry, OwnedEntry::from(first));
+            assert_eq!(conflicting.input_index, 1);
+        }
+        _ => panic!("Unexpected error: {}", merge_error),
+    }
+
+    let inputs = vec![vec![first], vec![same_path]];
+    let merge_result = merge_entries(&inputs, MergePolicy::CaseInsensitiveError).unwrap();
+    assert!(merge_result.conflicts.is_empty());
+}
+
+#[test]
+fn test_merge_empty_inputs() {
+    for inputs in [vec![], vec![Vec::new()], vec![Vec::new(), Vec::new()]] {
+        let merge_result = merge_entries(&inputs, MergePolicy::CaseInsensitiveError).unwrap();
+        assert!(merge_result.entries.is_empty());
+        assert!(merge_result.conflicts.is_empty());
+    }
+
+    let only_entry = entry("A.h", "/a/", "A.h");
+    let inputs = vec![Vec::new(), vec![only_entry], Vec::new()];
+    let merge_result = merge_entries(&inputs, MergePolicy::FirstWins).unwrap();
+    assert_eq!(merge_result.entries, vec![sourced_entry(1, only_entry)]);
+}
+
+#[test]
+fn test_merge_conflicts_across_many_inputs() {
+    let entries = [
+        entry("A.h", "/0/", "A.h"),
+        entry("A.h", "/1/", "A.h"),
+        entry("a.h", "/0/", "A.h"),
+        entry("A.h", "/3/", "A.h"),
+    ];
+    let inputs: Vec<_> = entries.iter().map(|entry| vec![*entry]).collect();
+
+    // Losers are listed in input order, entries mapping to the winner's
+    // path are not in conflict.
+    let merge_result = merge_entries(&inputs, MergePolicy::FirstWins).unwrap();
+    assert_eq!(merge_result.entries, vec![sourced_entry(0, entries[0])]);
+    assert_eq!(
+        merge_result.conflicts[0].losers,
+        vec![sourced_entry(1, entries[1]), sourced_entry(3, entries[3])]
+    );
+
+    let merge_result = merge_entries(&inputs, MergePolicy::LastWins).unwrap();
+    assert_eq!(merge_result.entries, vec![sourced_entry(3, entries[3])]);
+    assert_eq!(
+        merge_result.conflicts[0].losers,
+        vec![
+            sourced_entry(0, entries[0]),
+            sourced_entry(1, entries[1]),
+            sourced_entry(2, entries[2])
+        ]
+    );
+}
+
+#[test]
+fn test_merge_salvaged_entries() {
+    // Entries lost to corrupt buckets get filled in from another input.
+    let cut_off_bytes = test_data::read_sdwebimage_cut_off_binary_hmap();
+    let (salvaged_entries, bucket_errors) = parse_headermap_lenient(&cut_off_bytes).unwrap();
+    assert!(!bucket_errors.is_empty());
+    let intact_bytes = test_data::read_sdwebimage_binary_hmap();
+    let intact_entries = parse_headermap(&intact_bytes, true).unwrap();
+
+    let inputs = vec![salvaged_entries.clone(), intact_entries.clone()];
+    let merge_result = merge_entries(&inputs, MergePolicy::CaseInsensitiveError).unwrap();
+    assert!(merge_result.conflicts.is_empty());
+    assert_eq!(merge_result.entries.len(), intact_entries.len());
+    assert_eq!(
+        merge_result
+            .entries
+            .iter()
+            .filter(|sourced_entry| sourced_entry.input_index == 0)
+            .count(),
+        salvaged_entries.len()
+    );
+
+    let merged_bytes = build_headermap(
+        merge_result
+            .entries
+            .iter()
+            .map(|sourced_entry| sourced_entry.entry.as_entry()),
+    )
+    .unwrap();
+    assert!(validate_headermap(&merged_bytes).is_empty());
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{
+    diff_entries, parse_headermap, remap_entries, remap_headermap, Entry, OwnedEntry, RemapOptions,
+    RemapRule,
+};
+
+fn rule(from: &str, to: &str) -> RemapRule {
+    RemapRule {
+        from: from.to_owned(),
+        to: to.to_owned(),
+    }
+}
+
+mod test_data;
+
+const SDWEBIMAGE_ROOT: &str = "/Users/milen/Desktop/SDWebImage";
+
+#[test]
+fn test_sdwebimage_remap_round_trip() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let options = RemapOptions {
+        rules: vec![rule(SDWEBIMAGE_ROOT, "/src/")],
+        ..Default::default()
+    };
+    let remapped_bytes = remap_headermap(&bytes, &options).unwrap();
+    let remapped_entries = parse_headermap(&remapped_bytes, true).unwrap();
+    assert_eq!(remapped_entries.len(), entries.len());
+    assert!(remapped_entries
+        .iter()
+        .all(|entry| entry.prefix.starts_with("/src/") && !entry.suffix.is_empty()));
+
+    let options = RemapOptions {
+        rules: vec![rule("/src", SDWEBIMAGE_ROOT)],
+        ..Default::default()
+    };
+    let restored_entries = remap_entries(&remapped_entries, &options);
+    let restored_entries: Vec<_> = restored_entries.iter().map(OwnedEntry::as_entry).collect();
+    assert!(diff_entries(&entries, &restored_entries).is_empty());
+}
+
+#[test]
+fn test_remap_relative_to() {
+    let entries = [
+        Entry {
+            key: "A.h",
+            prefix: "/root/src/",
+            suffix: "A.h",
+        },
+        Entry {
+            key: "B.h",
+            prefix: "/root/",
+            suffix: "include/B.h",
+        },
+        Entry {
+            key: "C.h",
+            prefix: "/other/",
+            suffix: "C.h",
+        },
+    ];
+
+    let options = RemapOptions {
+        rules: vec![rule("/root/include", "/root/src/include")],
+        relative_to: Some("/root/src".to_owned()),
+    };
+    let mut remapped_entries = remap_entries(&entries, &options);
+    remapped_entries.sort();
+    assert_eq!(
+        remapped_entries,
+        vec![
+            OwnedEntry::new("A.h", "", "A.h"),
+            OwnedEntry::new("B.h", "", "include/B.h"),
+            OwnedEntry::new("C.h", "../../other/", "C.h"),
+        ]
+    );
+}
+
+#[test]
+fn test_remap_rule_matching() {
+    let entries = [
+        Entry {
+            key: "Nested.h",
+            prefix: "/a/b/",
+            suffix: "Nested.h",
+        },
+        Entry {
+            key: "Sibling.h",
+            prefix: "/a/",
+            suffix: "bc.h",
+        },
+        Entry {
+            key: "Split.h",
+            prefix: "/a/",
+            suffix: "b/Split.h",
+        },
+        Entry {
+            key: "Other.h",
+            prefix: "/other/",
+            suffix: "Other.h",
+        },
+    ];
+
+    // The first matching rule wins, `/a/b` does not match `/a/bc.h`.
+    let options = RemapOptions {
+        rules: vec![rule("/a/b/", "/x"), rule("/a", "/y/")],
+        ..Default::default()
+    };
+    assert_eq!(
+        remap_entries(&entries, &options),
+        vec![
+            OwnedEntry::new("Nested.h", "/x/", "Nested.h"),
+            OwnedEntry::new("Sibling.h", "/y/", "bc.h"),
+            // The suffix no longer matches, so the target gets split anew.
+            OwnedEntry::new("Split.h", "/x/", "Split.h"),
+            OwnedEntry::new("Other.h", "/other/", "Other.h"),
+        ]
+    );
+
+    // `/` matches every absolute target.
+    let options = RemapOptions {
+        rules: vec![rule("/", "/root")],
+        ..Default::default()
+    };
+    assert!(remap_entries(&entries, &options)
+        .iter()
+        .all(|entry| entry.prefix.starts_with("/root/")));
+}
+
+#[test]
+fn test_remap_empty_and_corrupt_headermaps() {
+    let options = RemapOptions {
+        rules: vec![rule("/a", "/b")],
+        ..Default::default()
+    };
+
+    let empty_bytes = test_data::build_hmap_bytes(&[]);
+    let remapped_bytes = remap_headermap(&empty_bytes, &options).unwrap();
+    assert!(parse_headermap(&remapped_bytes, true).unwrap().is_empty());
+
+    let cut_off_bytes = test_data::read_sdwebimage_cut_off_binary_hmap();
+    assert!(remap_headermap(&cut_off_bytes, &options).is_err());
+}
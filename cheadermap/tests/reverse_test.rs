@@ -0,0 +1,51 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{parse_headermap, HeaderMapBuilder, ReverseIndex};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_reverse_lookup() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let index = ReverseIndex::new(entries.iter().copied(), None);
+    assert_eq!(index.len(), entries.len());
+    let matching_entries =
+        index.lookup("/Users/milen/Desktop/SDWebImage/SDWebImage/Private/../Core//SDImageCache.h");
+    assert_eq!(matching_entries.len(), 1);
+    assert_eq!(matching_entries[0].key, "SDWebImage/SDImageCache.h");
+    assert!(index
+        .lookup("/Users/milen/Desktop/SDImageCache.h")
+        .is_empty());
+}
+
+#[test]
+fn test_reverse_lookup_all_keys() {
+    let mut builder = HeaderMapBuilder::new();
+    builder
+        .add_entry("Foo/Foo.h", "/src/Foo/", "Foo.h")
+        .unwrap();
+    builder.add_entry("Foo.h", "/src/Foo/./", "Foo.h").unwrap();
+    builder.add_entry("Bar.h", "include/", "Bar.h").unwrap();
+    builder
+        .add_entry("Foo/Bar.h", "../include/", "Bar.h")
+        .unwrap();
+    let bytes = builder.build().unwrap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let index = ReverseIndex::new(entries, Some("/src/build"));
+    let keys = |target_path| -> Vec<_> {
+        index
+            .lookup(target_path)
+            .iter()
+            .map(|entry| entry.key)
+            .collect()
+    };
+    assert_eq!(keys("/src/Foo/Foo.h"), vec!["Foo.h", "Foo/Foo.h"]);
+    assert_eq!(keys("/src/build/include/Bar.h"), vec!["Bar.h"]);
+    assert_eq!(keys("/src/include/Bar.h"), vec!["Foo/Bar.h"]);
+}
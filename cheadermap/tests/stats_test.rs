@@ -0,0 +1,124 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::collections::BTreeMap;
+
+use cheadermap::binary::{
+    headermap_stats, headermap_stats_lenient, print_stats, HeaderMapBuilder, StatsFormat,
+};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_stats() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let stats = headermap_stats(&bytes).unwrap();
+
+    assert_eq!(stats.entry_count, 75);
+    assert_eq!(stats.bucket_count(), 256);
+    assert_eq!(stats.probe_length_histogram.values().sum::<usize>(), 75);
+    assert!(stats.max_probe_length() <= stats.longest_cluster);
+    assert_eq!(stats.string_section_size, bytes.len() - 3096);
+    assert!(stats.deduplication_savings() > 0);
+    assert_eq!(
+        stats
+            .prefixes
+            .iter()
+            .map(|prefix_stats| prefix_stats.entry_count)
+            .sum::<usize>(),
+        75
+    );
+    assert_eq!(
+        stats.prefixes[0].prefix,
+        "/Users/milen/Desktop/SDWebImage/SDWebImage/Core/"
+    );
+
+    let mut json_bytes = Vec::new();
+    print_stats(&mut json_bytes, &stats, StatsFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json_bytes).unwrap();
+    assert_eq!(json["entry_count"], 75);
+    assert_eq!(
+        json["prefixes"].as_object().unwrap().len(),
+        stats.prefixes.len()
+    );
+}
+
+#[test]
+fn test_stats_string_deduplication() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("A.h", "/include/", "A.h").unwrap();
+    builder.add_entry("B.h", "/include/", "B.h").unwrap();
+    let bytes = builder.build().unwrap();
+    let stats = headermap_stats(&bytes).unwrap();
+
+    // `/include/` is shared, as are the keys and suffixes.
+    assert_eq!(stats.referenced_string_bytes, "A.h\0B.h\0/include/\0".len());
+    assert_eq!(
+        stats.undeduplicated_string_bytes,
+        2 * "A.h\0/include/\0A.h\0".len()
+    );
+    assert_eq!(stats.prefixes.len(), 1);
+    assert_eq!(stats.prefixes[0].entry_count, 2);
+}
+
+#[test]
+fn test_stats_lenient() {
+    let bytes = test_data::read_sdwebimage_cut_off_binary_hmap();
+    assert!(headermap_stats(&bytes).is_err());
+
+    let (stats, bucket_errors) = headermap_stats_lenient(&bytes).unwrap();
+    assert_eq!(stats.entry_count, 32);
+    assert_eq!(bucket_errors.len(), 43);
+    // The skipped buckets are still part of the clusters.
+    let intact_bytes = test_data::read_sdwebimage_binary_hmap();
+    let intact_stats = headermap_stats(&intact_bytes).unwrap();
+    assert_eq!(stats.longest_cluster, intact_stats.longest_cluster);
+
+    let bytes = test_data::get_truncated_bucket_table_hmap_bytes(1 << 31);
+    assert!(headermap_stats_lenient(&bytes).is_err());
+}
+
+#[test]
+fn test_stats_empty_headermap() {
+    let bytes = test_data::build_hmap_bytes(&[]);
+    let stats = headermap_stats(&bytes).unwrap();
+    assert_eq!(stats.entry_count, 0);
+    assert_eq!(stats.load_factor(), 0.0);
+    assert_eq!(stats.max_probe_length(), 0);
+    assert_eq!(stats.longest_cluster, 0);
+    assert_eq!(stats.deduplication_savings(), 0);
+    assert!(stats.prefixes.is_empty());
+
+    let mut json_bytes = Vec::new();
+    print_stats(&mut json_bytes, &stats, StatsFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json_bytes).unwrap();
+    assert_eq!(json["entry_count"], 0);
+    let mut text_bytes = Vec::new();
+    print_stats(&mut text_bytes, &stats, StatsFormat::Text).unwrap();
+    assert!(!text_bytes.is_empty());
+}
+
+#[test]
+fn test_stats_full_table_of_colliding_keys() {
+    let bytes = test_data::build_full_colliding_keys_hmap_bytes();
+    let stats = headermap_stats(&bytes).unwrap();
+
+    assert_eq!(stats.load_factor(), 1.0);
+    assert_eq!(
+        stats.probe_length_histogram,
+        BTreeMap::from([(1, 1), (2, 1), (3, 1), (4, 1)])
+    );
+    assert_eq!(stats.max_probe_length(), 4);
+    assert_eq!(stats.longest_cluster, 4);
+}
+
+#[test]
+fn test_stats_cluster_wraps_around() {
+    let buckets: Vec<_> = [6, 7, 0, 1, 3]
+        .iter()
+        .zip(["A.h", "B.h", "C.h", "D.h", "E.h"])
+        .map(|(bucket_index, key)| (*bucket_index, key, "/k/", key))
+        .collect();
+    let stats = headermap_stats(&test_data::build_raw_hmap_bytes(8, &buckets)).unwrap();
+    assert_eq!(stats.entry_count, 5);
+    assert_eq!(stats.longest_cluster, 4);
+}
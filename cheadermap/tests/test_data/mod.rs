@@ -1,7 +1,15 @@
 // Copyright (c) 2022 Milen Dzhumerov
 
+// Shared by multiple test crates, each of which only uses some of the helpers.
+#![allow(dead_code)]
+
 use std::path::PathBuf;
 
+use cheadermap::binary::{bucket_index_for_key, HeaderMapBuilder};
+
+const DATA_HEADER_SIZE: usize = 24;
+const BUCKET_SIZE: usize = 12;
+
 pub fn get_sdwebimage_binary_reference_json_output() -> PathBuf {
     get_path_for_test_resources_filename("SDWebImage-all-target-headers.reference-output.json")
 }
@@ -15,17 +23,35 @@ pub fn get_sdwebimage_binary_hmap_path() -> PathBuf {
 }
 
 pub fn get_sdwebimage_malformed_binary_hmap_paths() -> Vec<PathBuf> {
-    return vec![
+    vec![
         get_path_for_test_resources_filename(
             "SDWebImage-all-target-headers.malformed.header-only.hmap",
         ),
         get_path_for_test_resources_filename(
             "SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap",
         ),
-    ];
+    ]
+}
+
+pub fn get_sdwebimage_cut_off_binary_hmap_path() -> PathBuf {
+    get_path_for_test_resources_filename(
+        "SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap",
+    )
+}
+
+/// Returns the bytes of the SDWebImage hmap written by Xcode, which has
+/// 75 entries in 256 buckets.
+pub fn read_sdwebimage_binary_hmap() -> Vec<u8> {
+    std::fs::read(get_sdwebimage_binary_hmap_path()).unwrap()
 }
 
-fn get_path_for_test_resources_filename(filename: &str) -> PathBuf {
+/// Returns the bytes of the SDWebImage hmap whose string section is cut
+/// off, so only 32 of its 75 entries can be read.
+pub fn read_sdwebimage_cut_off_binary_hmap() -> Vec<u8> {
+    std::fs::read(get_sdwebimage_cut_off_binary_hmap_path()).unwrap()
+}
+
+pub fn get_path_for_test_resources_filename(filename: &str) -> PathBuf {
     let mut path = get_tests_resources_dir();
     path.push(filename);
     path
@@ -37,3 +63,126 @@ fn get_tests_resources_dir() -> PathBuf {
     path.push("tests");
     path
 }
+
+/// Returns a 25-byte headermap whose header claims `bucket_count` buckets,
+/// none of which are present in the file.
+pub fn get_truncated_bucket_table_hmap_bytes(bucket_count: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x68_6D_61_70u32.to_ne_bytes()); // magic
+    bytes.extend_from_slice(&1u16.to_ne_bytes()); // version
+    bytes.extend_from_slice(&0u16.to_ne_bytes()); // reserved
+    bytes.extend_from_slice(&24u32.to_ne_bytes()); // string_section_offset
+    bytes.extend_from_slice(&0u32.to_ne_bytes()); // string_count
+    bytes.extend_from_slice(&bucket_count.to_ne_bytes());
+    bytes.extend_from_slice(&0u32.to_ne_bytes()); // max_value_length
+    bytes.push(0x0);
+    bytes
+}
+
+/// Builds a headermap mapping each key to its target path.
+pub fn build_hmap_bytes(mappings: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = HeaderMapBuilder::new();
+    for (key, target_path) in mappings {
+        let file_name_index = target_path.rfind('/').map_or(0, |index| index + 1);
+        let (prefix, suffix) = target_path.split_at(file_name_index);
+        builder.add_entry(key, prefix, suffix).unwrap();
+    }
+    builder.build().unwrap()
+}
+
+/// Returns a headermap with `bucket_count` buckets in which each
+/// `(bucket_index, key, prefix, suffix)` is stored in the given bucket,
+/// regardless of the hash of its key. This allows building tables the
+/// builder never writes, e.g., full ones or ones with displaced entries.
+pub fn build_raw_hmap_bytes(bucket_count: u32, buckets: &[(u32, &str, &str, &str)]) -> Vec<u8> {
+    let string_section_offset = DATA_HEADER_SIZE + bucket_count as usize * BUCKET_SIZE;
+    // Offset 0 is reserved for empty buckets.
+    let mut strings = vec![0u8];
+    let mut add_string = |string: &str| {
+        let offset = strings.len() as u32;
+        strings.extend_from_slice(string.as_bytes());
+        strings.push(0);
+        offset
+    };
+
+    let mut bucket_table = vec![0u8; bucket_count as usize * BUCKET_SIZE];
+    for (bucket_index, key, prefix, suffix) in buckets {
+        let bucket_offset = *bucket_index as usize * BUCKET_SIZE;
+        for (field_index, string) in [key, prefix, suffix].iter().enumerate() {
+            let field_offset = bucket_offset + field_index * 4;
+            bucket_table[field_offset..field_offset + 4]
+                .copy_from_slice(&add_string(string).to_ne_bytes());
+        }
+    }
+
+    let max_value_length = buckets
+        .iter()
+        .map(|(_, _, prefix, suffix)| prefix.len() + suffix.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x68_6D_61_70u32.to_ne_bytes()); // magic
+    bytes.extend_from_slice(&1u16.to_ne_bytes()); // version
+    bytes.extend_from_slice(&0u16.to_ne_bytes()); // reserved
+    bytes.extend_from_slice(&(string_section_offset as u32).to_ne_bytes());
+    bytes.extend_from_slice(&(3 * buckets.len() as u32 + 1).to_ne_bytes()); // string_count
+    bytes.extend_from_slice(&bucket_count.to_ne_bytes());
+    bytes.extend_from_slice(&(max_value_length as u32).to_ne_bytes());
+    bytes.extend_from_slice(&bucket_table);
+    bytes.extend_from_slice(&strings);
+    bytes
+}
+
+/// Keys which all hash to the same bucket, as their bytes add up the same.
+pub const COLLIDING_KEYS: [&str; 4] = ["ab.h", "ba.h", "a.bh", "b.ah"];
+
+/// Returns a headermap whose 4 buckets are all occupied by
+/// `COLLIDING_KEYS`, in order of their probe length starting at 1.
+pub fn build_full_colliding_keys_hmap_bytes() -> Vec<u8> {
+    let home_bucket_index = bucket_index_for_key(COLLIDING_KEYS[0], 4);
+    let buckets: Vec<_> = COLLIDING_KEYS
+        .iter()
+        .enumerate()
+        .map(|(probe_count, key)| {
+            let bucket_index = (home_bucket_index + probe_count as u32) % 4;
+            (bucket_index, *key, "/k/", *key)
+        })
+        .collect();
+    build_raw_hmap_bytes(4, &buckets)
+}
+
+/// The string offsets stored in a bucket.
+#[derive(Debug, Clone, Copy)]
+pub enum BucketField {
+    Key = 0,
+    Prefix = 1,
+    Suffix = 2,
+}
+
+/// Points `field` of the bucket at `bucket_index` past the end of the file,
+/// so the bucket cannot be decoded.
+pub fn corrupt_bucket(bytes: &mut [u8], bucket_index: u32, field: BucketField) {
+    let field_offset = DATA_HEADER_SIZE + bucket_index as usize * BUCKET_SIZE + field as usize * 4;
+    // Offsets are relative to the string section, so this is past the end.
+    let string_offset = bytes.len() as u32;
+    bytes[field_offset..field_offset + 4].copy_from_slice(&string_offset.to_ne_bytes());
+}
+
+/// Returns the index of the bucket which stores `key`.
+pub fn bucket_index_of_key(bytes: &[u8], key: &str) -> u32 {
+    cheadermap::binary::entries(bytes)
+        .unwrap()
+        .filter_map(Result::ok)
+        .find(|(_, entry)| entry.key == key)
+        .unwrap()
+        .0
+}
+
+/// Returns an empty directory for the test called `name`.
+pub fn create_temp_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cheadermap-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use cheadermap::binary::{
+    print_lookup_trace, DataHeaderError, HeaderMap, HeaderMapBuilder, LookupOutcome,
+    ProbedBucketState,
+};
+
+mod test_data;
+
+#[test]
+fn test_trace_lookup_collisions() {
+    // Anagrams have the same hash, so they probe the same buckets.
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("ab.h", "/a/", "ab.h").unwrap();
+    builder.add_entry("ba.h", "/b/", "ba.h").unwrap();
+    let bytes = builder.build().unwrap();
+    let headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+
+    let trace = headermap.trace_lookup("BA.H");
+    let first_trace = headermap.trace_lookup("ab.h");
+    assert_eq!(trace.hash, first_trace.hash);
+    assert_eq!(
+        trace.start_bucket_index,
+        trace.hash & (trace.bucket_count - 1)
+    );
+    assert_eq!(trace.probes.len(), 2);
+    assert!(matches!(
+        trace.probes[0].state,
+        ProbedBucketState::Mismatch { entry, home_bucket_index }
+            if entry.key == "ab.h" && home_bucket_index == trace.start_bucket_index
+    ));
+    assert!(matches!(trace.outcome(), LookupOutcome::Hit(entry) if entry.key == "ba.h"));
+
+    let trace = headermap.trace_lookup("aa.h.b");
+    assert!(matches!(
+        trace.outcome(),
+        LookupOutcome::MissAtEmptyBucket(bucket_index) if bucket_index == trace.probes.last().unwrap().bucket_index
+    ));
+
+    let mut output = Vec::new();
+    print_lookup_trace(&mut output, &headermap.trace_lookup("ba.h")).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("`ab.h` does not match, its hash points to the same bucket"));
+    assert!(output.contains("Hit: ba.h -> /b/ba.h"));
+}
+
+#[test]
+fn test_trace_lookup_wrap_around() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("A.h", "/a/", "A.h").unwrap();
+    let mut bytes = builder.build().unwrap();
+
+    // Shrink the bucket table to a single, occupied bucket.
+    let headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+    let bucket_index = headermap.trace_lookup("A.h").start_bucket_index as usize;
+    let bucket_offset = 24 + bucket_index * 12;
+    bytes.copy_within(bucket_offset..bucket_offset + 12, 24);
+    bytes[16..20].copy_from_slice(&1u32.to_ne_bytes());
+
+    let headermap = HeaderMap::from_bytes(&bytes[..]).unwrap();
+    let trace = headermap.trace_lookup("B.h");
+    assert_eq!(trace.probes.len(), 1);
+    assert_eq!(trace.outcome(), LookupOutcome::MissAfterWrapAround);
+}
+
+#[test]
+fn test_trace_lookup_corrupt_buckets() {
+    // Anagrams have the same hash, so `ba.h` is probed after `ab.h`.
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("ab.h", "/a/", "ab.h").unwrap();
+    builder.add_entry("ba.h", "/b/", "ba.h").unwrap();
+    let bytes = builder.build().unwrap();
+    let start_bucket_index = HeaderMap::from_bytes(&bytes[..])
+        .unwrap()
+        .trace_lookup("ab.h")
+        .start_bucket_index as usize;
+    let bucket_offset = 24 + start_bucket_index * 12;
+
+    // An unreadable key gets skipped.
+    let mut corrupt_key_bytes = bytes.clone();
+    corrupt_key_bytes[bucket_offset..bucket_offset + 4].copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    assert!(HeaderMap::from_bytes(&corrupt_key_bytes[..]).is_err());
+    let headermap = HeaderMap::from_bytes_lenient(&corrupt_key_bytes[..]).unwrap();
+    assert_eq!(headermap.len(), 1);
+    assert_eq!(headermap.iter().count(), 1);
+    assert_eq!(headermap.lookup("BA.H").unwrap().prefix, "/b/");
+    let trace = headermap.trace_lookup("ba.h");
+    assert!(matches!(
+        trace.probes[0].state,
+        ProbedBucketState::Corrupt { key: None, .. }
+    ));
+    assert!(matches!(trace.outcome(), LookupOutcome::Hit(entry) if entry.key == "ba.h"));
+
+    // A matching key with an unreadable target ends the lookup.
+    let mut corrupt_suffix_bytes = bytes.clone();
+    corrupt_suffix_bytes[bucket_offset + 8..bucket_offset + 12]
+        .copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    let headermap = HeaderMap::from_bytes_lenient(&corrupt_suffix_bytes[..]).unwrap();
+    assert!(headermap.lookup("ab.h").is_none());
+    assert_eq!(headermap.lookup("ba.h").unwrap().prefix, "/b/");
+    let trace = headermap.trace_lookup("AB.H");
+    assert!(matches!(
+        trace.probes[0].state,
+        ProbedBucketState::Corrupt {
+            key: Some("ab.h"),
+            key_matches: true,
+            ..
+        }
+    ));
+    assert_eq!(
+        trace.outcome(),
+        LookupOutcome::MissAtCorruptBucket(start_bucket_index as u32)
+    );
+
+    let mut output = Vec::new();
+    print_lookup_trace(&mut output, &trace).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("`ab.h` matches, but its target cannot be read"));
+
+    // Like Clang, the bucket table has to fit into the file.
+    let bytes = test_data::get_truncated_bucket_table_hmap_bytes(1 << 26);
+    assert!(matches!(
+        HeaderMap::from_bytes_lenient(&bytes[..]),
+        Err(cheadermap::Error::Header(
+            DataHeaderError::BucketTableOutOfBounds(bucket_count)
+        )) if bucket_count == 1 << 26
+    ));
+}
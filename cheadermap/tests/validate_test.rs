@@ -0,0 +1,180 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{validate_headermap, FindingKind, HeaderMapBuilder, Severity};
+
+mod test_data;
+
+const DATA_HEADER_SIZE: usize = 24;
+const BUCKET_SIZE: usize = 12;
+
+fn read_bucket(bytes: &[u8], bucket_index: usize) -> [u8; BUCKET_SIZE] {
+    let offset = DATA_HEADER_SIZE + bucket_index * BUCKET_SIZE;
+    bytes[offset..offset + BUCKET_SIZE].try_into().unwrap()
+}
+
+fn write_bucket(bytes: &mut [u8], bucket_index: usize, bucket: &[u8; BUCKET_SIZE]) {
+    let offset = DATA_HEADER_SIZE + bucket_index * BUCKET_SIZE;
+    bytes[offset..offset + BUCKET_SIZE].copy_from_slice(bucket);
+}
+
+/// Returns a single entry headermap and the index of the occupied bucket.
+fn build_single_entry_headermap() -> (Vec<u8>, usize) {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("Foo.h", "/path/", "FOO.h").unwrap();
+    let bytes = builder.build().unwrap();
+
+    let bucket_index = (0..4)
+        .find(|bucket_index| read_bucket(&bytes, *bucket_index) != [0; BUCKET_SIZE])
+        .unwrap();
+    (bytes, bucket_index)
+}
+
+#[test]
+fn test_sdwebimage_valid() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+
+    let findings = validate_headermap(&bytes);
+    assert!(findings.is_empty(), "Unexpected findings: {:#?}", findings);
+}
+
+#[test]
+fn test_malformed_sdwebimage_hmaps() {
+    for hmap_path in test_data::get_sdwebimage_malformed_binary_hmap_paths() {
+        let bytes = fs::read(hmap_path).unwrap();
+        let findings = validate_headermap(&bytes);
+        assert!(findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error));
+    }
+}
+
+#[test]
+fn test_unreachable_entry() {
+    let (mut bytes, bucket_index) = build_single_entry_headermap();
+    let bucket = read_bucket(&bytes, bucket_index);
+    write_bucket(&mut bytes, bucket_index, &[0; BUCKET_SIZE]);
+    write_bucket(&mut bytes, (bucket_index + 1) % 4, &bucket);
+
+    let findings = validate_headermap(&bytes);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, Severity::Error);
+    assert_eq!(
+        findings[0].offset,
+        DATA_HEADER_SIZE + ((bucket_index + 1) % 4) * BUCKET_SIZE
+    );
+    assert!(matches!(
+        findings[0].kind,
+        FindingKind::UnreachableEntry { .. }
+    ));
+}
+
+#[test]
+fn test_case_insensitive_duplicate_key() {
+    let (mut bytes, bucket_index) = build_single_entry_headermap();
+    // Point the key of the next bucket to the "FOO.h" suffix string.
+    let mut bucket = read_bucket(&bytes, bucket_index);
+    let suffix_offset: [u8; 4] = bucket[8..12].try_into().unwrap();
+    bucket[0..4].copy_from_slice(&suffix_offset);
+    write_bucket(&mut bytes, (bucket_index + 1) % 4, &bucket);
+
+    // Account for the extra entry, so only the duplicate key is reported.
+    bytes[12..16].copy_from_slice(&2u32.to_ne_bytes());
+
+    let findings = validate_headermap(&bytes);
+    // Clang never finds the shadowed entry, so it's an error.
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, Severity::Error);
+    assert!(matches!(
+        &findings[0].kind,
+        FindingKind::DuplicateKey { key, shadowing_key, .. } if key == "FOO.h" && shadowing_key == "Foo.h"
+    ));
+}
+
+#[test]
+fn test_string_section_overlaps_bucket_table() {
+    let (mut bytes, _) = build_single_entry_headermap();
+    let string_section_offset = (DATA_HEADER_SIZE + BUCKET_SIZE) as u32;
+    bytes[8..12].copy_from_slice(&string_section_offset.to_ne_bytes());
+
+    let findings = validate_headermap(&bytes);
+    assert!(findings.iter().any(|finding| matches!(
+        finding.kind,
+        FindingKind::StringSectionOverlapsBucketTable { .. }
+    )));
+}
+
+#[test]
+fn test_entry_behind_corrupt_matching_bucket() {
+    let (mut bytes, bucket_index) = build_single_entry_headermap();
+    // Move the entry one bucket further and leave a copy with an unreadable
+    // suffix in its home bucket, Clang stops probing there.
+    let bucket = read_bucket(&bytes, bucket_index);
+    let next_bucket_index = (bucket_index + 1) % 4;
+    write_bucket(&mut bytes, next_bucket_index, &bucket);
+    let mut corrupt_bucket = bucket;
+    corrupt_bucket[8..12].copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    write_bucket(&mut bytes, bucket_index, &corrupt_bucket);
+
+    let findings = validate_headermap(&bytes);
+    assert_eq!(findings.len(), 2, "Unexpected findings: {:#?}", findings);
+    assert!(matches!(findings[0].kind, FindingKind::InvalidBucket(_)));
+    assert_eq!(findings[1].severity, Severity::Error);
+    assert!(matches!(
+        findings[1].kind,
+        FindingKind::UnreachableEntry {
+            stop_bucket_index,
+            ..
+        } if stop_bucket_index as usize == bucket_index
+    ));
+
+    // A corrupt bucket whose key cannot be read gets skipped.
+    corrupt_bucket[0..4].copy_from_slice(&0xFFFFu32.to_ne_bytes());
+    write_bucket(&mut bytes, bucket_index, &corrupt_bucket);
+    let findings = validate_headermap(&bytes);
+    assert_eq!(findings.len(), 1, "Unexpected findings: {:#?}", findings);
+    assert!(matches!(findings[0].kind, FindingKind::InvalidBucket(_)));
+}
+
+#[test]
+fn test_header_counts_mismatch() {
+    let (bytes, _) = build_single_entry_headermap();
+    let findings_at = |bytes: &[u8], string_count: u32, max_value_length: u32| {
+        let mut bytes = bytes.to_vec();
+        bytes[12..16].copy_from_slice(&string_count.to_ne_bytes());
+        bytes[20..24].copy_from_slice(&max_value_length.to_ne_bytes());
+        validate_headermap(&bytes)
+    };
+
+    // One entry can have up to three strings plus the empty string, and a
+    // zero max value length is what Xcode writes.
+    let value_length = ("/path/".len() + "FOO.h".len()) as u32;
+    for string_count in 1..=4 {
+        assert!(findings_at(&bytes, string_count, value_length).is_empty());
+    }
+    assert!(findings_at(&bytes, 1, 0).is_empty());
+
+    for (string_count, max_value_length) in [(0, value_length), (5, value_length)] {
+        let findings = findings_at(&bytes, string_count, max_value_length);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].offset, 12);
+        assert!(matches!(
+            findings[0].kind,
+            FindingKind::StringCountMismatch { entry_count: 1, .. }
+        ));
+    }
+
+    for max_value_length in [value_length - 1, value_length + 1] {
+        let findings = findings_at(&bytes, 1, max_value_length);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].offset, 20);
+        assert!(matches!(
+            findings[0].kind,
+            FindingKind::MaxValueLengthMismatch { .. }
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{
+    bucket_index_for_key, entries, parse_header, parse_headermap, parse_json_entries,
+    repair_headermap, validate_headermap, HeaderMap, HeaderMapBuilder, OwnedEntry,
+};
+
+mod test_data;
+
+use test_data::BucketField;
+
+#[test]
+fn test_sdwebimage_round_trip() {
+    let bytes = test_data::read_sdwebimage_binary_hmap();
+    let mut entries = parse_headermap(&bytes, true).unwrap();
+
+    let mut builder = HeaderMapBuilder::new();
+    for entry in &entries {
+        builder
+            .add_entry(entry.key, entry.prefix, entry.suffix)
+            .unwrap();
+    }
+    let written_bytes = builder.build().unwrap();
+
+    let mut written_entries = parse_headermap(&written_bytes, true).unwrap();
+    assert_eq!(entries.len(), written_entries.len());
+
+    entries.sort_by(|lhs, rhs| lhs.key.cmp(rhs.key));
+    written_entries.sort_by(|lhs, rhs| lhs.key.cmp(rhs.key));
+    for (entry, written_entry) in entries.iter().zip(written_entries.iter()) {
+        assert_eq!(entry.key, written_entry.key);
+        assert_eq!(entry.prefix, written_entry.prefix);
+        assert_eq!(entry.suffix, written_entry.suffix);
+    }
+}
+
+#[test]
+fn test_empty_headermap() {
+    let written_bytes = HeaderMapBuilder::new().build().unwrap();
+    let entries = parse_headermap(&written_bytes, true).unwrap();
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn test_empty_strings() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("Foo.h", "", "Foo.h").unwrap();
+    let written_bytes = builder.build().unwrap();
+
+    let entries = parse_headermap(&written_bytes, true).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].prefix, "");
+    assert_eq!(entries[0].suffix, "Foo.h");
+}
+
+#[test]
+fn test_non_ascii_key_round_trip() {
+    // Added first, so it lands in the bucket Clang starts probing at.
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("€.h", "/a/", "€.h").unwrap();
+    // Enough entries for 512 buckets: sign- and zero-extending the three
+    // non-ASCII bytes only give different buckets past 256 buckets.
+    for index in 0..100 {
+        let key = format!("Header{}.h", index);
+        builder.add_entry(&key, "/a/", &key).unwrap();
+    }
+    let written_bytes = builder.build().unwrap();
+
+    let bucket_count = parse_header(&written_bytes).unwrap().header.bucket_count;
+    assert_eq!(bucket_count, 512);
+    // Clang: (-30 + -126 + -84 + '.' + 'h') * 13 = -1170, masked to 366.
+    let bucket_index = entries(&written_bytes)
+        .unwrap()
+        .map(|entry_result| entry_result.unwrap())
+        .find(|(_, entry)| entry.key == "€.h")
+        .map(|(bucket_index, _)| bucket_index);
+    assert_eq!(bucket_index, Some(366));
+
+    let headermap = HeaderMap::from_bytes(written_bytes).unwrap();
+    let entry = headermap.lookup("€.H").unwrap();
+    assert_eq!(entry.prefix, "/a/");
+    assert_eq!(entry.suffix, "€.h");
+}
+
+#[test]
+fn test_case_insensitive_duplicate_key() {
+    let mut builder = HeaderMapBuilder::new();
+    builder.add_entry("Foo/Bar.h", "/a/", "Bar.h").unwrap();
+    assert!(builder.add_entry("foo/bar.h", "/b/", "Bar.h").is_err());
+    assert_eq!(builder.len(), 1);
+}
+
+#[test]
+fn test_repair_sdwebimage_hmaps() {
+    let intact_bytes = test_data::read_sdwebimage_binary_hmap();
+    let intact_headermap = HeaderMap::from_bytes(&intact_bytes[..]).unwrap();
+
+    let (repaired_bytes, report) = repair_headermap(&intact_bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, 75);
+    assert!(report.bucket_errors.is_empty());
+    assert!(report.duplicate_entries.is_empty());
+    assert_eq!(HeaderMap::from_bytes(repaired_bytes).unwrap().len(), 75);
+
+    let cut_off_bytes = test_data::read_sdwebimage_cut_off_binary_hmap();
+    let (repaired_bytes, report) = repair_headermap(&cut_off_bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, 32);
+    assert_eq!(report.bucket_errors.len(), 43);
+
+    let repaired_headermap = HeaderMap::from_bytes(repaired_bytes).unwrap();
+    assert_eq!(repaired_headermap.len(), 32);
+    for entry in repaired_headermap.iter() {
+        assert_eq!(intact_headermap.lookup(entry.key), Some(entry));
+    }
+}
+
+#[test]
+fn test_repair_empty_headermap() {
+    let bytes = test_data::build_raw_hmap_bytes(4, &[]);
+    let (repaired_bytes, report) = repair_headermap(&bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, 0);
+    assert!(report.bucket_errors.is_empty());
+    assert!(HeaderMap::from_bytes(repaired_bytes).unwrap().is_empty());
+}
+
+#[test]
+fn test_repair_corrupt_buckets() {
+    let mut bytes =
+        test_data::build_hmap_bytes(&[("A.h", "/a/A.h"), ("B.h", "/b/B.h"), ("C.h", "/c/C.h")]);
+    let key_bucket_index = test_data::bucket_index_of_key(&bytes, "A.h");
+    let suffix_bucket_index = test_data::bucket_index_of_key(&bytes, "B.h");
+    test_data::corrupt_bucket(&mut bytes, key_bucket_index, BucketField::Key);
+    test_data::corrupt_bucket(&mut bytes, suffix_bucket_index, BucketField::Suffix);
+
+    let (repaired_bytes, report) = repair_headermap(&bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, 1);
+    let error_bucket_indices: Vec<_> = report
+        .bucket_errors
+        .iter()
+        .map(|bucket_error| bucket_error.bucket_index)
+        .collect();
+    let mut expected_bucket_indices = vec![key_bucket_index, suffix_bucket_index];
+    expected_bucket_indices.sort_unstable();
+    assert_eq!(error_bucket_indices, expected_bucket_indices);
+
+    let repaired_headermap = HeaderMap::from_bytes(repaired_bytes).unwrap();
+    assert_eq!(repaired_headermap.len(), 1);
+    assert_eq!(
+        repaired_headermap.lookup("c.h").unwrap().target_path(),
+        std::path::PathBuf::from("/c/C.h")
+    );
+}
+
+#[test]
+fn test_repair_keeps_entry_clang_resolves_to() {
+    // Of the keys which collide case-insensitively, Clang finds the one
+    // closest to the bucket of their hash, no matter the bucket order.
+    let home_bucket_index = bucket_index_for_key("a.h", 4);
+    let bytes = test_data::build_raw_hmap_bytes(
+        4,
+        &[
+            ((home_bucket_index + 2) % 4, "A.H", "/third/", "A.H"),
+            (home_bucket_index, "a.h", "/first/", "a.h"),
+            ((home_bucket_index + 1) % 4, "A.h", "/second/", "A.h"),
+        ],
+    );
+    assert_eq!(
+        HeaderMap::from_bytes(&bytes[..])
+            .unwrap()
+            .lookup("A.h")
+            .unwrap()
+            .prefix,
+        "/first/"
+    );
+
+    let (repaired_bytes, report) = repair_headermap(&bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, 1);
+    assert_eq!(
+        report.duplicate_entries,
+        vec![
+            OwnedEntry::new("A.h", "/second/", "A.h"),
+            OwnedEntry::new("A.H", "/third/", "A.H"),
+        ]
+    );
+    let repaired_headermap = HeaderMap::from_bytes(repaired_bytes).unwrap();
+    assert_eq!(repaired_headermap.lookup("A.h").unwrap().prefix, "/first/");
+}
+
+#[test]
+fn test_repair_full_table() {
+    // Without an empty bucket, Clang never stops probing for missing keys.
+    let keys = ["K0.h", "K1.h", "K2.h", "K3.h"];
+    let buckets: Vec<_> = keys
+        .iter()
+        .enumerate()
+        .map(|(bucket_index, key)| (bucket_index as u32, *key, "/k/", *key))
+        .collect();
+    let bytes = test_data::build_raw_hmap_bytes(4, &buckets);
+    assert!(!validate_headermap(&bytes).is_empty());
+
+    let (repaired_bytes, report) = repair_headermap(&bytes).unwrap();
+    assert_eq!(report.recovered_entry_count, keys.len());
+    assert!(validate_headermap(&repaired_bytes).is_empty());
+    let repaired_headermap = HeaderMap::from_bytes(repaired_bytes).unwrap();
+    assert!(repaired_headermap.header().header.bucket_count > 4);
+    for key in keys {
+        assert_eq!(repaired_headermap.lookup(key).unwrap().suffix, key);
+    }
+    assert!(repaired_headermap.lookup("Missing.h").is_none());
+}
+
+#[test]
+fn test_create_from_reference_json() {
+    let json_bytes = fs::read(test_data::get_sdwebimage_binary_reference_json_output()).unwrap();
+    let json_entries = parse_json_entries(&json_bytes).unwrap();
+
+    let mut builder = HeaderMapBuilder::new();
+    for entry in &json_entries {
+        builder
+            .add_entry(&entry.key, &entry.prefix, &entry.suffix)
+            .unwrap();
+    }
+    let created_headermap = HeaderMap::from_bytes(builder.build().unwrap()).unwrap();
+
+    let intact_bytes = test_data::read_sdwebimage_binary_hmap();
+    let intact_headermap = HeaderMap::from_bytes(&intact_bytes[..]).unwrap();
+    assert_eq!(created_headermap.len(), intact_headermap.len());
+    for entry in intact_headermap.iter() {
+        assert_eq!(created_headermap.get(entry.key), Some(entry));
+    }
+}
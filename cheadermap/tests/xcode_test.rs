@@ -0,0 +1,122 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::fs;
+
+use cheadermap::binary::{
+    diff_entries, generate_xcode_headermap, parse_headermap, parse_target_descriptions,
+    HeaderVisibility, OwnedEntry, TargetDescription, TargetHeader, XcodeHeaderMapKind,
+};
+
+mod test_data;
+
+#[test]
+fn test_sdwebimage_all_target_headers() {
+    let binary_hmap_path = test_data::get_sdwebimage_binary_hmap_path();
+    let bytes = fs::read(binary_hmap_path).unwrap();
+    let entries = parse_headermap(&bytes, true).unwrap();
+
+    let mut targets: Vec<TargetDescription> = Vec::new();
+    for entry in &entries {
+        let (product_name, _) = entry.key.split_once('/').unwrap();
+        let header = TargetHeader {
+            path: entry.target_path().to_str().unwrap().to_owned(),
+            visibility: HeaderVisibility::Public,
+        };
+        match targets
+            .iter_mut()
+            .find(|target| target.product_name == product_name)
+        {
+            Some(target) => target.headers.push(header),
+            None => targets.push(TargetDescription {
+                name: product_name.to_owned(),
+                product_name: product_name.to_owned(),
+                framework: true,
+                headers: vec![header],
+            }),
+        }
+    }
+    assert_eq!(targets.len(), 2);
+
+    let headermap = generate_xcode_headermap(&targets, 0, XcodeHeaderMapKind::AllTargetHeaders);
+    assert_eq!(headermap.file_name(), "SDWebImage-all-target-headers.hmap");
+    assert!(headermap.entries.duplicate_entries.is_empty());
+
+    let generated_entries: Vec<_> = headermap
+        .entries
+        .entries
+        .iter()
+        .map(OwnedEntry::as_entry)
+        .collect();
+    assert!(diff_entries(&entries, &generated_entries).is_empty());
+}
+
+#[test]
+fn test_xcode_headermap_kinds() {
+    let json = br#"{
+        "targets": [
+            {
+                "name": "App",
+                "headers": [{"path": "/app/AppDelegate.h"}]
+            },
+            {
+                "name": "KitTarget",
+                "product_name": "Kit",
+                "framework": true,
+                "headers": [
+                    {"path": "/kit/Kit.h", "visibility": "public"},
+                    {"path": "/kit/KitPrivate.h", "visibility": "private"},
+                    {"path": "/kit/Internal.h", "visibility": "project"}
+                ]
+            }
+        ]
+    }"#;
+    let targets = parse_target_descriptions(json).unwrap();
+    assert_eq!(targets[0].product_name, "App");
+    assert!(!targets[0].framework);
+
+    let keys = |target_index: usize, kind: XcodeHeaderMapKind| -> Vec<String> {
+        generate_xcode_headermap(&targets, target_index, kind)
+            .entries
+            .entries
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect()
+    };
+
+    assert_eq!(
+        keys(1, XcodeHeaderMapKind::ProjectHeaders),
+        ["Kit.h", "KitPrivate.h", "Internal.h", "AppDelegate.h"]
+    );
+    assert_eq!(
+        keys(1, XcodeHeaderMapKind::OwnTargetHeaders),
+        [
+            "Kit.h",
+            "Kit/Kit.h",
+            "KitPrivate.h",
+            "Kit/KitPrivate.h",
+            "Internal.h"
+        ]
+    );
+    assert_eq!(
+        keys(0, XcodeHeaderMapKind::OwnTargetHeaders),
+        ["AppDelegate.h"]
+    );
+    assert_eq!(
+        keys(0, XcodeHeaderMapKind::AllTargetHeaders),
+        ["Kit/Kit.h", "Kit/KitPrivate.h"]
+    );
+    assert_eq!(
+        keys(1, XcodeHeaderMapKind::AllNonFrameworkTargetHeaders),
+        ["AppDelegate.h"]
+    );
+    assert_eq!(
+        keys(0, XcodeHeaderMapKind::AllProductHeaders),
+        ["Kit/Kit.h", "Kit/KitPrivate.h"]
+    );
+
+    assert!(parse_target_descriptions(br#"{"targets": [{"headers": []}]}"#).is_err());
+    assert!(parse_target_descriptions(
+        br#"{"targets": [{"name": "A", "headers": [{"path": "A.h", "visibility": "open"}]}]}"#
+    )
+    .is_err());
+}
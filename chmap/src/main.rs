@@ -1,6 +1,12 @@
 // Copyright (c) 2022 Milen Dzhumerov
 
 use clap::Parser;
+use std::io::Write;
+
+/// Exit code used when a command completed, but its result was negative
+/// (e.g., a key did not resolve). It's distinct from `EXIT_FAILURE` and
+/// from the exit code used by clap for usage errors.
+const EXIT_NEGATIVE_RESULT: i32 = 3;
 
 #[derive(clap::Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -14,40 +20,1450 @@ struct Cli {
 #[derive(clap::Subcommand)]
 enum Commands {
     Print(PrintCommand),
+    Lookup(LookupCommand),
+    Validate(ValidateCommand),
+    Repair(RepairCommand),
+    Create(CreateCommand),
+    Convert(ConvertCommand),
+    Merge(MergeCommand),
+    Diff(DiffCommand),
+    Generate(GenerateCommand),
+    GenerateXcode(GenerateXcodeCommand),
+    Remap(RemapCommand),
+    Expand(ExpandCommand),
+    Filter(FilterCommand),
+    Set(SetCommand),
+    Rm(RmCommand),
+    Mv(MvCommand),
+    Edit(EditCommand),
+    WhoMaps(WhoMapsCommand),
+    Stats(StatsCommand),
+    Inspect(InspectCommand),
+    ExplainLookup(ExplainLookupCommand),
+    Hash(HashCommand),
 }
 
 #[derive(Copy, Debug, Clone, clap::ArgEnum)]
 enum PrintOutputFormat {
     Text,
+    /// `{"key": {"prefix": "...", "suffix": "..."}}`
     Json,
+    /// `{"key": "prefix + suffix"}`
+    JsonFlat,
+    /// `{"mappings": {"key": "prefix + suffix"}}`, as used by LLVM's `hmaptool`
+    Hmaptool,
+    /// A rules_ios hmap params file
+    Params,
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum TextInputFormat {
+    /// Either `{"key": {"prefix": "...", "suffix": "..."}}` or `{"key": "path"}`
+    Json,
+    /// `{"mappings": {"key": "path"}}`, as used by LLVM's `hmaptool`
+    Hmaptool,
+    /// A rules_ios hmap params file
+    Params,
+}
+
+impl TextInputFormat {
+    fn to_input_format(self) -> cheadermap::binary::InputFormat {
+        match self {
+            TextInputFormat::Json => cheadermap::binary::InputFormat::Json,
+            TextInputFormat::Hmaptool => cheadermap::binary::InputFormat::Hmaptool,
+            TextInputFormat::Params => cheadermap::binary::InputFormat::Params,
+        }
+    }
 }
 
 #[derive(clap::Parser, Debug)]
 #[clap(author, version, about = "Print the hmap entries", long_about = None)]
 struct PrintCommand {
     /// The output format.
-    #[clap(short, long, arg_enum, default_value_t = PrintOutputFormat::Text)]
+    #[clap(short, long, alias = "output-format", arg_enum, default_value_t = PrintOutputFormat::Text)]
     format: PrintOutputFormat,
 
+    /// Print every recoverable entry instead of failing on corrupt buckets.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Include the data header in the output (JSON formats only).
+    #[clap(long)]
+    header: bool,
+
     /// Path to the hmap file.
     #[clap(required = true, parse(from_os_str))]
     path: std::path::PathBuf,
 }
 
-impl PrintCommand {
-    fn execute(&self) -> anyhow::Result<()> {
-        let format = match self.format {
+impl PrintOutputFormat {
+    fn to_output_format(self) -> cheadermap::binary::OutputFormat {
+        match self {
             PrintOutputFormat::Text => cheadermap::binary::OutputFormat::Text,
             PrintOutputFormat::Json => cheadermap::binary::OutputFormat::Json,
+            PrintOutputFormat::JsonFlat => cheadermap::binary::OutputFormat::JsonFlat,
+            PrintOutputFormat::Hmaptool => cheadermap::binary::OutputFormat::Hmaptool,
+            PrintOutputFormat::Params => cheadermap::binary::OutputFormat::Params,
+        }
+    }
+}
+
+/// Parses the entries of the headermap read from `path`. In lenient mode,
+/// corrupt buckets get skipped and reported on stderr, otherwise they're
+/// an error.
+fn parse_entries<'a>(
+    path: &std::path::Path,
+    bytes: &'a [u8],
+    lenient: bool,
+) -> anyhow::Result<Vec<cheadermap::binary::Entry<'a>>> {
+    if !lenient {
+        return Ok(cheadermap::binary::parse_headermap(bytes, true)?);
+    }
+
+    let (entries, bucket_errors) = cheadermap::binary::parse_headermap_lenient(bytes)?;
+    report_skipped_buckets(path, entries.len(), &bucket_errors);
+
+    Ok(entries)
+}
+
+/// Reports the buckets skipped in lenient mode on stderr.
+fn report_skipped_buckets(
+    path: &std::path::Path,
+    entry_count: usize,
+    bucket_errors: &[cheadermap::binary::BucketError],
+) {
+    if bucket_errors.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "{}: skipped {} corrupt bucket(s), recovered {} entries:",
+        path.display(),
+        bucket_errors.len(),
+        entry_count
+    );
+    for bucket_error in bucket_errors {
+        eprintln!("  {}", bucket_error);
+    }
+}
+
+/// Reports the entries which get skipped when printing `entries` in the
+/// params format on stderr. Does nothing for other formats.
+fn report_unrepresentable_params_entries(
+    entries: &[cheadermap::binary::Entry],
+    format: cheadermap::binary::OutputFormat,
+) {
+    if format != cheadermap::binary::OutputFormat::Params {
+        return;
+    }
+
+    let skipped_entries = cheadermap::binary::unrepresentable_params_entries(entries);
+    if skipped_entries.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "Skipped {} entries which cannot be represented as params:",
+        skipped_entries.len()
+    );
+    for entry in skipped_entries {
+        eprintln!("  {}", entry);
+    }
+}
+
+impl PrintCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let mut entries = parse_entries(&self.path, &file_bytes, self.lenient)?;
+        entries.sort_by(|lhs, rhs| lhs.key.cmp(rhs.key));
+
+        let header = if self.header {
+            Some(cheadermap::binary::parse_header(&file_bytes)?)
+        } else {
+            None
+        };
+
+        let output_format = self.format.to_output_format();
+        cheadermap::binary::print_entries_with_header(
+            &mut std::io::stdout(),
+            &entries,
+            header.as_ref(),
+            output_format,
+        )?;
+        report_unrepresentable_params_entries(&entries, output_format);
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Resolve include keys like Clang does",
+    long_about = "Resolve include keys like Clang does. Prints one result per key argument, in order and keyed by the key as given, so repeated keys are printed repeatedly (as repeated members in JSON). Keys which do not resolve are printed as `KEY: not found` or map to `null` in JSON. Exits with code 3 if any key did not resolve."
+)]
+struct LookupCommand {
+    /// The output format.
+    #[clap(short, long, arg_enum, default_value_t = PrintOutputFormat::Text)]
+    format: PrintOutputFormat,
+
+    /// Skip corrupt buckets the way Clang does instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// The keys to resolve, as spelled in `#include` directives.
+    #[clap(required = true)]
+    keys: Vec<String>,
+}
+
+impl LookupCommand {
+    fn execute(&self) -> anyhow::Result<i32> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let headermap = if self.lenient {
+            let headermap = cheadermap::binary::HeaderMap::from_bytes_lenient(&file_bytes[..])?;
+            report_skipped_buckets(&self.path, headermap.len(), headermap.bucket_errors());
+            headermap
+        } else {
+            cheadermap::binary::HeaderMap::from_bytes(&file_bytes[..])?
+        };
+
+        let results: Vec<_> = self
+            .keys
+            .iter()
+            .map(|key| (key.as_str(), headermap.lookup(key)))
+            .collect();
+
+        let output_format = self.format.to_output_format();
+        cheadermap::binary::print_lookup_results(&mut std::io::stdout(), &results, output_format)?;
+
+        let missing_keys = results.iter().filter(|(_, entry)| entry.is_none());
+        if output_format == cheadermap::binary::OutputFormat::Params {
+            for (key, _) in missing_keys.clone() {
+                eprintln!("{}: not found", key);
+            }
+            let found_entries: Vec<_> = results
+                .iter()
+                .filter_map(|(key, entry)| {
+                    entry.map(|entry| cheadermap::binary::Entry { key, ..entry })
+                })
+                .collect();
+            report_unrepresentable_params_entries(&found_entries, output_format);
+        }
+
+        if missing_keys.count() == 0 {
+            Ok(libc::EXIT_SUCCESS)
+        } else {
+            Ok(EXIT_NEGATIVE_RESULT)
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Check the hmap structure and Clang compatibility",
+    long_about = "Check the hmap structure and Clang compatibility. Exits with a non-zero code if any errors were found."
+)]
+struct ValidateCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+}
+
+impl ValidateCommand {
+    fn execute(&self) -> anyhow::Result<i32> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let findings = cheadermap::binary::validate_headermap(&file_bytes);
+
+        let mut stdout = std::io::stdout();
+        for finding in &findings {
+            writeln!(stdout, "{}", finding)?;
+        }
+
+        let error_count = findings
+            .iter()
+            .filter(|finding| finding.severity == cheadermap::binary::Severity::Error)
+            .count();
+        let warning_count = findings.len() - error_count;
+        writeln!(
+            stdout,
+            "{} error(s), {} warning(s)",
+            error_count, warning_count
+        )?;
+
+        if error_count == 0 {
+            Ok(libc::EXIT_SUCCESS)
+        } else {
+            Ok(libc::EXIT_FAILURE)
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Rewrite a damaged hmap into a valid one",
+    long_about = "Rewrite a damaged hmap into a valid one. Every decodable entry gets salvaged and the dropped ones get reported on stderr."
+)]
+struct RepairCommand {
+    /// Path to the damaged hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the repaired hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl RepairCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let (repaired_bytes, report) = cheadermap::binary::repair_headermap(&file_bytes)?;
+        std::fs::write(&self.output, repaired_bytes)?;
+
+        eprintln!(
+            "Recovered {} entries, dropped {} corrupt bucket(s) and {} duplicate key(s)",
+            report.recovered_entry_count,
+            report.bucket_errors.len(),
+            report.duplicate_entries.len()
+        );
+        for bucket_error in &report.bucket_errors {
+            eprintln!("  {}", bucket_error);
+        }
+        for duplicate_entry in &report.duplicate_entries {
+            eprintln!("  Duplicate key: {}", duplicate_entry);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a file or, if `path` is `-`, stdin.
+fn read_input(path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
+    if path == std::path::Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes)?;
+        Ok(bytes)
+    } else {
+        Ok(std::fs::read(path)?)
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Create an hmap from a JSON mapping or params file",
+    long_about = "Create an hmap from a JSON mapping or params file. By default, the input is either `{\"key\": {\"prefix\": \"...\", \"suffix\": \"...\"}}` or `{\"key\": \"path\"}`, as printed by `chmap print --format json`."
+)]
+struct CreateCommand {
+    /// The input format.
+    #[clap(short, long, arg_enum, default_value_t = TextInputFormat::Json)]
+    input_format: TextInputFormat,
+
+    /// Path to the input file, `-` reads from stdin.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl CreateCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let input_bytes = read_input(&self.path)?;
+        let entries =
+            cheadermap::binary::parse_entries(&input_bytes, self.input_format.to_input_format())?;
+
+        let headermap_bytes =
+            cheadermap::binary::build_headermap(entries.iter().map(|entry| entry.as_entry()))?;
+        std::fs::write(&self.output, headermap_bytes)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum ConvertInputFormat {
+    /// A binary hmap file
+    Hmap,
+    /// Either `{"key": {"prefix": "...", "suffix": "..."}}` or `{"key": "path"}`
+    Json,
+    /// `{"mappings": {"key": "path"}}`, as used by LLVM's `hmaptool`
+    Hmaptool,
+    /// A rules_ios hmap params file
+    Params,
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum ConvertOutputFormat {
+    /// A binary hmap file
+    Hmap,
+    Text,
+    /// `{"key": {"prefix": "...", "suffix": "..."}}`
+    Json,
+    /// `{"key": "prefix + suffix"}`
+    JsonFlat,
+    /// `{"mappings": {"key": "prefix + suffix"}}`, as used by LLVM's `hmaptool`
+    Hmaptool,
+    /// A rules_ios hmap params file
+    Params,
+}
+
+impl ConvertOutputFormat {
+    /// Returns `None` for the binary format.
+    fn to_output_format(self) -> Option<cheadermap::binary::OutputFormat> {
+        match self {
+            ConvertOutputFormat::Hmap => None,
+            ConvertOutputFormat::Text => Some(cheadermap::binary::OutputFormat::Text),
+            ConvertOutputFormat::Json => Some(cheadermap::binary::OutputFormat::Json),
+            ConvertOutputFormat::JsonFlat => Some(cheadermap::binary::OutputFormat::JsonFlat),
+            ConvertOutputFormat::Hmaptool => Some(cheadermap::binary::OutputFormat::Hmaptool),
+            ConvertOutputFormat::Params => Some(cheadermap::binary::OutputFormat::Params),
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Convert between hmap files and other mapping formats",
+    long_about = None
+)]
+struct ConvertCommand {
+    /// The input format.
+    #[clap(short, long, arg_enum, default_value_t = ConvertInputFormat::Hmap)]
+    input_format: ConvertInputFormat,
+
+    /// The output format.
+    #[clap(short = 'f', long, arg_enum, default_value_t = ConvertOutputFormat::Hmap)]
+    output_format: ConvertOutputFormat,
+
+    /// Skip corrupt buckets of binary hmap input instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the input file, `-` reads from stdin.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the output to. If omitted, writes to stdout.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl ConvertCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let input_bytes = read_input(&self.path)?;
+        let entries: Vec<cheadermap::binary::OwnedEntry> = match self.input_format {
+            ConvertInputFormat::Hmap => parse_entries(&self.path, &input_bytes, self.lenient)?
+                .into_iter()
+                .map(cheadermap::binary::OwnedEntry::from)
+                .collect(),
+            ConvertInputFormat::Json => cheadermap::binary::parse_entries(
+                &input_bytes,
+                cheadermap::binary::InputFormat::Json,
+            )?,
+            ConvertInputFormat::Hmaptool => cheadermap::binary::parse_entries(
+                &input_bytes,
+                cheadermap::binary::InputFormat::Hmaptool,
+            )?,
+            ConvertInputFormat::Params => cheadermap::binary::parse_entries(
+                &input_bytes,
+                cheadermap::binary::InputFormat::Params,
+            )?,
+        };
+
+        let mut borrowed_entries: Vec<_> = entries.iter().map(|entry| entry.as_entry()).collect();
+        write_entries(
+            &mut borrowed_entries,
+            self.output_format,
+            self.output.as_deref(),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum MergeConflictPolicy {
+    /// The entry from the earliest input wins
+    FirstWins,
+    /// The entry from the latest input wins
+    LastWins,
+    /// Fail on identical keys mapping to different paths
+    Error,
+    /// Fail on keys mapping to different paths which only differ in case
+    CaseInsensitiveError,
+}
+
+impl MergeConflictPolicy {
+    fn to_merge_policy(self) -> cheadermap::binary::MergePolicy {
+        match self {
+            MergeConflictPolicy::FirstWins => cheadermap::binary::MergePolicy::FirstWins,
+            MergeConflictPolicy::LastWins => cheadermap::binary::MergePolicy::LastWins,
+            MergeConflictPolicy::Error => cheadermap::binary::MergePolicy::Error,
+            MergeConflictPolicy::CaseInsensitiveError => {
+                cheadermap::binary::MergePolicy::CaseInsensitiveError
+            }
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Merge several hmaps into one",
+    long_about = "Merge several hmaps into one. Resolved conflicts get reported on stderr, together with the input each winning entry came from."
+)]
+struct MergeCommand {
+    /// How to resolve keys which map to different paths.
+    #[clap(short, long, arg_enum, default_value_t = MergeConflictPolicy::Error)]
+    policy: MergeConflictPolicy,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Paths to the hmap files, in order.
+    #[clap(required = true, parse(from_os_str))]
+    paths: Vec<std::path::PathBuf>,
+
+    /// Path to write the merged hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl MergeCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let inputs_bytes = self
+            .paths
+            .iter()
+            .map(std::fs::read)
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs = self
+            .paths
+            .iter()
+            .zip(&inputs_bytes)
+            .map(|(path, bytes)| parse_entries(path, bytes, self.lenient))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let input_path = |sourced_entry: &cheadermap::binary::SourcedEntry| {
+            self.paths[sourced_entry.input_index].display().to_string()
+        };
+        let merge_result =
+            match cheadermap::binary::merge_entries(&inputs, self.policy.to_merge_policy()) {
+                Ok(merge_result) => merge_result,
+                Err(cheadermap::Error::Merge(cheadermap::binary::MergeError::Conflict {
+                    existing,
+                    conflicting,
+                })) => anyhow::bail!(
+                    "Conflicting entries: `{}` from `{}` and `{}` from `{}`",
+                    existing.entry,
+                    input_path(&existing),
+                    conflicting.entry,
+                    input_path(&conflicting)
+                ),
+                Err(err) => return Err(err.into()),
+            };
+
+        let headermap_bytes = cheadermap::binary::build_headermap(
+            merge_result
+                .entries
+                .iter()
+                .map(|sourced_entry| sourced_entry.entry.as_entry()),
+        )?;
+        std::fs::write(&self.output, headermap_bytes)?;
+
+        if !merge_result.conflicts.is_empty() {
+            eprintln!(
+                "Resolved {} conflicting key(s):",
+                merge_result.conflicts.len()
+            );
+        }
+        for conflict in &merge_result.conflicts {
+            eprintln!(
+                "  {} (from `{}`)",
+                conflict.winner.entry,
+                input_path(&conflict.winner)
+            );
+            for loser in &conflict.losers {
+                eprintln!("    dropped {} (from `{}`)", loser.entry, input_path(loser));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum DiffOutputFormat {
+    /// One line per difference, prefixed by `+` (added), `-` (removed) or `~` (changed)
+    Text,
+    /// `{"added": {...}, "removed": {...}, "changed": {...}}`
+    Json,
+}
+
+impl DiffOutputFormat {
+    fn to_diff_format(self) -> cheadermap::binary::DiffFormat {
+        match self {
+            DiffOutputFormat::Text => cheadermap::binary::DiffFormat::Text,
+            DiffOutputFormat::Json => cheadermap::binary::DiffFormat::Json,
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Compare the entries of two hmaps",
+    long_about = "Compare the entries of two hmaps by key. Exits with code 3 if the hmaps differ."
+)]
+struct DiffCommand {
+    /// The output format.
+    #[clap(short, long, arg_enum, default_value_t = DiffOutputFormat::Text)]
+    format: DiffOutputFormat,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the old hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    old_path: std::path::PathBuf,
+
+    /// Path to the new hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    new_path: std::path::PathBuf,
+}
+
+impl DiffCommand {
+    fn execute(&self) -> anyhow::Result<i32> {
+        let old_bytes = std::fs::read(&self.old_path)?;
+        let new_bytes = std::fs::read(&self.new_path)?;
+        let old_entries = parse_entries(&self.old_path, &old_bytes, self.lenient)?;
+        let new_entries = parse_entries(&self.new_path, &new_bytes, self.lenient)?;
+
+        let diff = cheadermap::binary::diff_entries(&old_entries, &new_entries);
+        cheadermap::binary::print_diff(
+            &mut std::io::stdout(),
+            &diff,
+            self.format.to_diff_format(),
+        )?;
+
+        if diff.is_empty() {
+            Ok(libc::EXIT_SUCCESS)
+        } else {
+            Ok(EXIT_NEGATIVE_RESULT)
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Generate an hmap by scanning header directories",
+    long_about = "Generate an hmap by scanning header directories. Each header gets mapped by its file name and by `<namespace>/<file name>`, the namespace defaults to the name of the scanned directory. If keys collide, the first header wins and the dropped ones get reported on stderr."
+)]
+struct GenerateCommand {
+    /// Directory to scan recursively, can be repeated.
+    #[clap(short, long = "dir", required = true, parse(from_os_str))]
+    dirs: Vec<std::path::PathBuf>,
+
+    /// Header file extension (case-insensitive), can be repeated. Defaults to `h`, `hpp`, `hh` and `inc`.
+    #[clap(short, long = "extension")]
+    extensions: Vec<String>,
+
+    /// The namespace of the `<namespace>/<file name>` keys. Defaults to the
+    /// name of the scanned directory each header was found in.
+    #[clap(short, long)]
+    namespace: Option<String>,
+
+    /// Path to write the hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl GenerateCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let mut options = cheadermap::binary::GenerateOptions {
+            namespace: self.namespace.clone(),
+            ..Default::default()
+        };
+        if !self.extensions.is_empty() {
+            options.extensions = self.extensions.clone();
+        }
+
+        let generated_entries =
+            cheadermap::binary::generate_headermap_entries(&self.dirs, &options)?;
+        let headermap_bytes = cheadermap::binary::build_headermap(
+            generated_entries
+                .entries
+                .iter()
+                .map(|entry| entry.as_entry()),
+        )?;
+        std::fs::write(&self.output, headermap_bytes)?;
+
+        for duplicate_entry in &generated_entries.duplicate_entries {
+            eprintln!("Duplicate key: {}", duplicate_entry);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Generate Xcode-style hmaps from a JSON target description",
+    long_about = "Generate Xcode-style hmaps from a JSON target description. For each target, writes `<Target>-project-headers.hmap`, `<Target>-own-target-headers.hmap`, `<Target>-all-target-headers.hmap`, `<Target>-all-non-framework-target-headers.hmap` and `<Target>-all-product-headers.hmap`. Colliding keys get reported on stderr."
+)]
+struct GenerateXcodeCommand {
+    /// Path to the JSON target description, `-` reads from stdin.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Directory to write the hmap files to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output_dir: std::path::PathBuf,
+}
+
+impl GenerateXcodeCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let input_bytes = read_input(&self.path)?;
+        let targets = cheadermap::binary::parse_target_descriptions(&input_bytes)?;
+
+        std::fs::create_dir_all(&self.output_dir)?;
+        for headermap in cheadermap::binary::generate_xcode_headermaps(&targets) {
+            let headermap_bytes = cheadermap::binary::build_headermap(
+                headermap
+                    .entries
+                    .entries
+                    .iter()
+                    .map(|entry| entry.as_entry()),
+            )?;
+            std::fs::write(self.output_dir.join(headermap.file_name()), headermap_bytes)?;
+
+            for duplicate_entry in &headermap.entries.duplicate_entries {
+                eprintln!(
+                    "{}: duplicate key: {}",
+                    headermap.file_name(),
+                    duplicate_entry
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Rewrite the directories of hmap targets",
+    long_about = "Rewrite the directories of hmap targets, e.g., to make hmaps machine-independent. `--from` and `--to` can be repeated, the first matching pair rewrites a target."
+)]
+struct RemapCommand {
+    /// Directory to rewrite, matched on whole path components.
+    #[clap(long)]
+    from: Vec<String>,
+
+    /// Directory to rewrite the corresponding `--from` directory to.
+    #[clap(long)]
+    to: Vec<String>,
+
+    /// Make absolute targets relative to this directory.
+    #[clap(long)]
+    relative_to: Option<String>,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the remapped hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl RemapCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        if self.from.len() != self.to.len() {
+            anyhow::bail!(
+                "Each `--from` needs a corresponding `--to`, found {} and {}",
+                self.from.len(),
+                self.to.len()
+            );
+        }
+
+        let options = cheadermap::binary::RemapOptions {
+            rules: self
+                .from
+                .iter()
+                .zip(&self.to)
+                .map(|(from, to)| cheadermap::binary::RemapRule {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+                .collect(),
+            relative_to: self.relative_to.clone(),
+        };
+
+        let file_bytes = std::fs::read(&self.path)?;
+        let entries = parse_entries(&self.path, &file_bytes, self.lenient)?;
+        let remapped_entries = cheadermap::binary::remap_entries(&entries, &options);
+        let headermap_bytes = cheadermap::binary::build_headermap(
+            remapped_entries.iter().map(|entry| entry.as_entry()),
+        )?;
+        std::fs::write(&self.output, headermap_bytes)?;
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Expand placeholders in hmap targets",
+    long_about = "Expand `$(NAME)` and `${NAME}` placeholders in hmap targets. Fails if any placeholder refers to an undefined variable."
+)]
+struct ExpandCommand {
+    /// Variable definition in the form `NAME=VALUE`, can be repeated.
+    /// Takes precedence over variable files.
+    #[clap(long = "var")]
+    vars: Vec<String>,
+
+    /// File with one `NAME=VALUE` definition per line, can be repeated.
+    /// Later files take precedence.
+    #[clap(long = "var-file", parse(from_os_str))]
+    var_files: Vec<std::path::PathBuf>,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the template hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the expanded hmap file to.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    output: std::path::PathBuf,
+}
+
+impl ExpandCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let mut variables = std::collections::HashMap::new();
+        for var_file in &self.var_files {
+            variables.extend(cheadermap::binary::parse_variable_file(&std::fs::read(
+                var_file,
+            )?)?);
+        }
+        for var in &self.vars {
+            let (name, value) = cheadermap::binary::parse_variable_definition(var)?;
+            variables.insert(name, value);
+        }
+
+        let file_bytes = std::fs::read(&self.path)?;
+        let entries = parse_entries(&self.path, &file_bytes, self.lenient)?;
+        let expanded_entries = match cheadermap::binary::expand_entries(&entries, &variables) {
+            Ok(expanded_entries) => expanded_entries,
+            Err(cheadermap::Error::Expand(expand_error)) => anyhow::bail!("{}", expand_error),
+            Err(err) => return Err(err.into()),
         };
+        let headermap_bytes = cheadermap::binary::build_headermap(
+            expanded_entries.iter().map(|entry| entry.as_entry()),
+        )?;
+        std::fs::write(&self.output, headermap_bytes)?;
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Select hmap entries by key or target",
+    long_about = "Select hmap entries by key or target and write them as a new hmap or in a textual format. Options of the same kind are alternatives, options of different kinds all have to match. Globs have to match the whole string, `*` and `?` do not match `/` while `**` matches anything."
+)]
+struct FilterCommand {
+    /// Glob the key has to match, can be repeated.
+    #[clap(short, long = "key")]
+    keys: Vec<String>,
+
+    /// Regex the key has to match, can be repeated.
+    #[clap(long = "key-regex")]
+    key_regexes: Vec<String>,
+
+    /// Glob the target path has to match, can be repeated.
+    #[clap(short, long = "target")]
+    targets: Vec<String>,
+
+    /// Regex the target path has to match, can be repeated.
+    #[clap(long = "target-regex")]
+    target_regexes: Vec<String>,
+
+    /// File with one key per line, the key has to be listed (case-insensitively).
+    #[clap(long, parse(from_os_str))]
+    keys_from: Option<std::path::PathBuf>,
+
+    /// Select the entries which do not match.
+    #[clap(long)]
+    invert: bool,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// The output format.
+    #[clap(short = 'f', long, arg_enum, default_value_t = ConvertOutputFormat::Hmap)]
+    output_format: ConvertOutputFormat,
+
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the output to. If omitted, writes to stdout.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl FilterCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let mut filter = cheadermap::binary::EntryFilter::new();
+        for glob in &self.keys {
+            filter.add_key_glob(glob)?;
+        }
+        for pattern in &self.key_regexes {
+            filter.add_key_regex(pattern)?;
+        }
+        for glob in &self.targets {
+            filter.add_target_glob(glob)?;
+        }
+        for pattern in &self.target_regexes {
+            filter.add_target_regex(pattern)?;
+        }
+        if let Some(keys_from) = &self.keys_from {
+            filter.add_keys(cheadermap::binary::parse_key_list(&std::fs::read(
+                keys_from,
+            )?));
+        }
+        filter.set_invert(self.invert);
+
+        let file_bytes = std::fs::read(&self.path)?;
+        let entries = parse_entries(&self.path, &file_bytes, self.lenient)?;
+        let mut filtered_entries = filter.filter(&entries);
+        write_entries(
+            &mut filtered_entries,
+            self.output_format,
+            self.output.as_deref(),
+        )
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Add or replace an hmap entry",
+    long_about = "Add an entry or replace the entry with the same (case-insensitive) key. The hmap is updated in place, unless its bucket table is too full, in which case it gets rebuilt."
+)]
+struct SetCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// The key of the entry.
+    #[clap(required = true)]
+    key: String,
+
+    /// The target path of the entry.
+    #[clap(required = true)]
+    target_path: String,
+
+    /// Path to write the edited hmap file to. If omitted, overwrites the hmap file.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl SetCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let entry = cheadermap::binary::OwnedEntry::from_target_path(&self.key, &self.target_path);
+        edit_file(
+            &self.path,
+            self.output.as_deref(),
+            &[cheadermap::binary::EditOperation::Set(entry)],
+        )
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(author, version, about = "Remove hmap entries", long_about = None)]
+struct RmCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
 
-        cheadermap::binary::print_headermap(&mut std::io::stdout(), &self.path, format)
+    /// The keys of the entries to remove.
+    #[clap(required = true)]
+    keys: Vec<String>,
+
+    /// Path to write the edited hmap file to. If omitted, overwrites the hmap file.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl RmCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let operations: Vec<_> = self
+            .keys
+            .iter()
+            .map(|key| cheadermap::binary::EditOperation::Remove(key.clone()))
+            .collect();
+        edit_file(&self.path, self.output.as_deref(), &operations)
     }
 }
 
-fn execute_command(command: &Commands) -> anyhow::Result<()> {
+#[derive(clap::Parser, Debug)]
+#[clap(author, version, about = "Change the key of an hmap entry", long_about = None)]
+struct MvCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// The current key of the entry.
+    #[clap(required = true)]
+    from: String,
+
+    /// The new key of the entry.
+    #[clap(required = true)]
+    to: String,
+
+    /// Path to write the edited hmap file to. If omitted, overwrites the hmap file.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl MvCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        edit_file(
+            &self.path,
+            self.output.as_deref(),
+            &[cheadermap::binary::EditOperation::Rename {
+                from: self.from.clone(),
+                to: self.to.clone(),
+            }],
+        )
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Apply an edit script to an hmap",
+    long_about = "Apply an edit script to an hmap. The script has one operation per line: `add KEY PATH`, `set KEY PATH`, `replace KEY PATH`, `remove KEY` or `rename KEY NEW_KEY`. Empty lines and lines starting with `#` are ignored. Either all operations are applied or none."
+)]
+struct EditCommand {
+    /// Path to the edit script.
+    #[clap(short, long, required = true, parse(from_os_str))]
+    script: std::path::PathBuf,
+
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// Path to write the edited hmap file to. If omitted, overwrites the hmap file.
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
+
+impl EditCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let operations = match cheadermap::binary::parse_edit_script(&std::fs::read(&self.script)?)
+        {
+            Ok(operations) => operations,
+            Err(cheadermap::Error::Edit(edit_error)) => anyhow::bail!("{}", edit_error),
+            Err(err) => return Err(err.into()),
+        };
+        edit_file(&self.path, self.output.as_deref(), &operations)
+    }
+}
+
+/// Applies `operations` to the hmap at `path` and writes the result to
+/// `output` or, if `output` is `None`, back to `path`.
+fn edit_file(
+    path: &std::path::Path,
+    output: Option<&std::path::Path>,
+    operations: &[cheadermap::binary::EditOperation],
+) -> anyhow::Result<()> {
+    let file_bytes = std::fs::read(path)?;
+    let (headermap_bytes, outcome) =
+        match cheadermap::binary::edit_headermap(&file_bytes, operations) {
+            Ok(result) => result,
+            Err(cheadermap::Error::Edit(edit_error)) => anyhow::bail!("{}", edit_error),
+            Err(err) => return Err(err.into()),
+        };
+    if outcome == cheadermap::binary::EditOutcome::Rebuilt {
+        eprintln!("Note: the bucket table is full, the hmap got rebuilt.");
+    }
+    write_file_atomically(output.unwrap_or(path), &headermap_bytes)?;
+
+    Ok(())
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it over
+/// `path`, so `path` never contains a partially written hmap. If `path` is
+/// a symlink, its target gets replaced. The permissions of an existing
+/// file are kept.
+fn write_file_atomically(path: &std::path::Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let path = match std::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => path.to_owned(),
+        Err(error) => return Err(error.into()),
+    };
+    let permissions = match std::fs::metadata(&path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error.into()),
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid output path: {}", path.display()))?;
+    let mut temp_file_name = std::ffi::OsString::from(".");
+    temp_file_name.push(file_name);
+    temp_file_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_file_name);
+
+    let result = std::fs::write(&temp_path, bytes)
+        .and_then(|_| match permissions {
+            Some(permissions) => std::fs::set_permissions(&temp_path, permissions),
+            None => Ok(()),
+        })
+        .and_then(|_| std::fs::rename(&temp_path, &path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    Ok(result?)
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "List the hmap keys which resolve to a file",
+    long_about = "List the hmap keys which resolve to a file, i.e., every include spelling which reaches it. Exits with code 3 if no key resolves to the file."
+)]
+struct WhoMapsCommand {
+    /// Directory relative targets are resolved against. Defaults to the
+    /// current directory, like Clang.
+    #[clap(long, parse(from_os_str))]
+    base_dir: Option<std::path::PathBuf>,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the header file, it does not have to exist.
+    #[clap(required = true, parse(from_os_str))]
+    file: std::path::PathBuf,
+
+    /// Paths to the hmap files.
+    #[clap(required = true, parse(from_os_str))]
+    paths: Vec<std::path::PathBuf>,
+}
+
+impl WhoMapsCommand {
+    fn execute(&self) -> anyhow::Result<i32> {
+        let current_dir = std::env::current_dir()?;
+        let base_dir = current_dir.join(self.base_dir.as_deref().unwrap_or(&current_dir));
+        let base_dir = path_to_str(&base_dir)?;
+
+        // Targets can refer to the file through a symlinked directory
+        // or directly, so look up both spellings.
+        let mut file_paths = vec![path_to_str(&current_dir.join(&self.file))?.to_owned()];
+        if let Ok(canonical_path) = std::fs::canonicalize(&self.file) {
+            file_paths.push(path_to_str(&canonical_path)?.to_owned());
+        }
+
+        let mut found = false;
+        for path in &self.paths {
+            let file_bytes = std::fs::read(path)?;
+            let entries = parse_entries(path, &file_bytes, self.lenient)?;
+            let index = cheadermap::binary::ReverseIndex::new(entries, Some(base_dir));
+
+            let mut matching_entries: Vec<_> = file_paths
+                .iter()
+                .flat_map(|file_path| index.lookup(file_path))
+                .collect();
+            matching_entries.sort();
+            matching_entries.dedup();
+            for entry in matching_entries {
+                println!("{}: {}", path.display(), entry.key);
+                found = true;
+            }
+        }
+
+        if found {
+            Ok(libc::EXIT_SUCCESS)
+        } else {
+            Ok(EXIT_NEGATIVE_RESULT)
+        }
+    }
+}
+
+#[derive(Copy, Debug, Clone, clap::ArgEnum)]
+enum StatsOutputFormat {
+    Text,
+    Json,
+}
+
+impl StatsOutputFormat {
+    fn to_stats_format(self) -> cheadermap::binary::StatsFormat {
+        match self {
+            StatsOutputFormat::Text => cheadermap::binary::StatsFormat::Text,
+            StatsOutputFormat::Json => cheadermap::binary::StatsFormat::Json,
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Print hmap size and hash table statistics",
+    long_about = "Print the entry and bucket counts, the load factor, the number of buckets Clang probes per lookup, the string section size and the bytes saved by sharing strings, as well as the number of entries per prefix."
+)]
+struct StatsCommand {
+    /// The output format.
+    #[clap(short, long, arg_enum, default_value_t = StatsOutputFormat::Text)]
+    format: StatsOutputFormat,
+
+    /// Skip corrupt buckets instead of failing.
+    /// Skipped buckets get reported on stderr.
+    #[clap(long)]
+    lenient: bool,
+
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+}
+
+impl StatsCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let stats = if self.lenient {
+            let (stats, bucket_errors) = cheadermap::binary::headermap_stats_lenient(&file_bytes)?;
+            report_skipped_buckets(&self.path, stats.entry_count, &bucket_errors);
+            stats
+        } else {
+            cheadermap::binary::headermap_stats(&file_bytes)?
+        };
+        cheadermap::binary::print_stats(
+            &mut std::io::stdout(),
+            &stats,
+            self.format.to_stats_format(),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Print the annotated byte layout of an hmap",
+    long_about = "Print every data header field and every bucket, empty ones included, together with their byte offsets, as well as the string section ranges which no bucket refers to. Header values which Clang rejects are printed as well."
+)]
+struct InspectCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+}
+
+impl InspectCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let layout = cheadermap::binary::inspect_headermap(&file_bytes)?;
+        cheadermap::binary::print_layout(&mut std::io::stdout(), &layout, &file_bytes)?;
+
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Explain how Clang looks up a key in an hmap",
+    long_about = "Explain how Clang looks up a key in an hmap: print the hash of the key, the bucket probing starts at and every probed bucket together with why its key did not match. Corrupt buckets are shown the way Clang handles them, so damaged hmaps can be explained too. Exits with code 3 if the key does not resolve."
+)]
+struct ExplainLookupCommand {
+    /// Path to the hmap file.
+    #[clap(required = true, parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    /// The key to look up, as spelled in the `#include` directive.
+    #[clap(required = true)]
+    key: String,
+}
+
+impl ExplainLookupCommand {
+    fn execute(&self) -> anyhow::Result<i32> {
+        let file_bytes = std::fs::read(&self.path)?;
+        let headermap = cheadermap::binary::HeaderMap::from_bytes_lenient(&file_bytes[..])?;
+        let trace = headermap.trace_lookup(&self.key);
+        cheadermap::binary::print_lookup_trace(&mut std::io::stdout(), &trace)?;
+
+        match trace.outcome() {
+            cheadermap::binary::LookupOutcome::Hit(_) => Ok(libc::EXIT_SUCCESS),
+            _ => Ok(EXIT_NEGATIVE_RESULT),
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about = "Print the Clang hmap hash of keys",
+    long_about = "Print the Clang hmap hash of keys and, if a bucket count is given, the bucket Clang starts probing at. Prints one `HASH [BUCKET] KEY` line per key."
+)]
+struct HashCommand {
+    /// The number of buckets, has to be a power of two.
+    #[clap(short, long)]
+    bucket_count: Option<u32>,
+
+    /// The keys to hash.
+    #[clap(required = true)]
+    keys: Vec<String>,
+}
+
+impl HashCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        if let Some(bucket_count) = self.bucket_count {
+            if !bucket_count.is_power_of_two() {
+                anyhow::bail!(
+                    "Bucket count is not a power of two, found `{}`",
+                    bucket_count
+                );
+            }
+        }
+
+        for key in &self.keys {
+            let hash = cheadermap::binary::hash_key(key);
+            match self.bucket_count {
+                Some(bucket_count) => println!(
+                    "{} {} {}",
+                    hash,
+                    cheadermap::binary::bucket_index_for_key(key, bucket_count),
+                    key
+                ),
+                None => println!("{} {}", hash, key),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn path_to_str(path: &std::path::Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow::anyhow!("Path is not valid UTF-8: {}", path.display()))
+}
+
+/// Writes `entries` as an hmap or, sorted by key, in a textual format to
+/// a file or, if `output` is `None`, to stdout.
+fn write_entries(
+    entries: &mut [cheadermap::binary::Entry<'_>],
+    format: ConvertOutputFormat,
+    output: Option<&std::path::Path>,
+) -> anyhow::Result<()> {
+    let mut output = open_output(output)?;
+    match format.to_output_format() {
+        None => {
+            let headermap_bytes = cheadermap::binary::build_headermap(entries.iter().copied())?;
+            output.write_all(&headermap_bytes)?;
+        }
+        Some(output_format) => {
+            entries.sort();
+            cheadermap::binary::print_entries(&mut output, entries, output_format)?;
+            report_unrepresentable_params_entries(entries, output_format);
+        }
+    }
+    output.flush()?;
+
+    Ok(())
+}
+
+/// Opens a file for writing or, if `path` is `None`, returns stdout.
+fn open_output(path: Option<&std::path::Path>) -> anyhow::Result<Box<dyn std::io::Write>> {
+    match path {
+        Some(path) => Ok(Box::new(std::io::BufWriter::new(std::fs::File::create(
+            path,
+        )?))),
+        None => Ok(Box::new(std::io::stdout())),
+    }
+}
+
+/// Returns the exit code of the command.
+fn execute_command(command: &Commands) -> anyhow::Result<i32> {
     match command {
-        Commands::Print(print_command) => print_command.execute(),
+        Commands::Print(print_command) => print_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Lookup(lookup_command) => lookup_command.execute(),
+        Commands::Validate(validate_command) => validate_command.execute(),
+        Commands::Repair(repair_command) => repair_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Create(create_command) => create_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Convert(convert_command) => convert_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Merge(merge_command) => merge_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Diff(diff_command) => diff_command.execute(),
+        Commands::Generate(generate_command) => {
+            generate_command.execute().map(|_| libc::EXIT_SUCCESS)
+        }
+        Commands::GenerateXcode(generate_xcode_command) => {
+            generate_xcode_command.execute().map(|_| libc::EXIT_SUCCESS)
+        }
+        Commands::Remap(remap_command) => remap_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Expand(expand_command) => expand_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Filter(filter_command) => filter_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Set(set_command) => set_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Rm(rm_command) => rm_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Mv(mv_command) => mv_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Edit(edit_command) => edit_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::WhoMaps(who_maps_command) => who_maps_command.execute(),
+        Commands::Stats(stats_command) => stats_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::Inspect(inspect_command) => inspect_command.execute().map(|_| libc::EXIT_SUCCESS),
+        Commands::ExplainLookup(explain_lookup_command) => explain_lookup_command.execute(),
+        Commands::Hash(hash_command) => hash_command.execute().map(|_| libc::EXIT_SUCCESS),
     }
 }
 
@@ -55,7 +1471,7 @@ fn main() {
     let cli = Cli::parse();
     let command_result = execute_command(&cli.command);
     let exit_code = match command_result {
-        Ok(_) => libc::EXIT_SUCCESS,
+        Ok(exit_code) => exit_code,
         Err(err) => {
             eprintln!("{:#?}", err);
             libc::EXIT_FAILURE
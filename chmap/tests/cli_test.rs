@@ -0,0 +1,267 @@
+// Copyright (c) 2022 Milen Dzhumerov
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_NEGATIVE_RESULT: i32 = 3;
+
+fn get_test_resource_path(filename: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("../cheadermap/resources/tests");
+    path.push(filename);
+    path
+}
+
+fn get_sdwebimage_binary_hmap_path() -> PathBuf {
+    get_test_resource_path("SDWebImage-all-target-headers.hmap")
+}
+
+fn get_sdwebimage_cut_off_binary_hmap_path() -> PathBuf {
+    get_test_resource_path("SDWebImage-all-target-headers.malformed.string-section-cut-off.hmap")
+}
+
+/// Returns an empty directory for the test called `name`.
+fn get_temp_dir(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("chmap-cli-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn run_chmap<I, S>(args: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    Command::new(env!("CARGO_BIN_EXE_chmap"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn assert_exit_code(output: &Output, exit_code: i32) {
+    assert_eq!(output.status.code(), Some(exit_code), "{:?}", output);
+}
+
+fn lookup_target(path: &Path, key: &str) -> String {
+    let output = run_chmap([
+        "lookup".as_ref(),
+        "--format".as_ref(),
+        "json-flat".as_ref(),
+        path.as_os_str(),
+        key.as_ref(),
+    ]);
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_lookup_exit_codes() {
+    let hmap_path = get_sdwebimage_binary_hmap_path();
+    let lookup = |key: &str| run_chmap(["lookup".as_ref(), hmap_path.as_os_str(), key.as_ref()]);
+    assert_exit_code(&lookup("sdwebimage/sdwebimage.h"), EXIT_SUCCESS);
+    assert_exit_code(&lookup("Missing.h"), EXIT_NEGATIVE_RESULT);
+
+    let output = run_chmap(["lookup", "/nonexistent/file.hmap", "A.h"]);
+    assert_exit_code(&output, EXIT_FAILURE);
+}
+
+#[test]
+fn test_lookup_lenient() {
+    let hmap_path = get_sdwebimage_cut_off_binary_hmap_path();
+    let output = run_chmap([
+        "lookup".as_ref(),
+        hmap_path.as_os_str(),
+        "Missing.h".as_ref(),
+    ]);
+    assert_exit_code(&output, EXIT_FAILURE);
+
+    let output = run_chmap([
+        "lookup".as_ref(),
+        "--lenient".as_ref(),
+        hmap_path.as_os_str(),
+        "Missing.h".as_ref(),
+    ]);
+    assert_exit_code(&output, EXIT_NEGATIVE_RESULT);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("skipped 43 corrupt bucket(s), recovered 32 entries"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_lookup_prints_repeated_keys() {
+    let hmap_path = get_sdwebimage_binary_hmap_path();
+    let output = run_chmap([
+        "lookup".as_ref(),
+        hmap_path.as_os_str(),
+        "SDWebImage/SDWebImage.h".as_ref(),
+        "Missing.h".as_ref(),
+        "sdwebimage/sdwebimage.h".as_ref(),
+        "SDWebImage/SDWebImage.h".as_ref(),
+    ]);
+    assert_exit_code(&output, EXIT_NEGATIVE_RESULT);
+    let target_path = "/Users/milen/Desktop/SDWebImage/WebImage/SDWebImage.h";
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "SDWebImage/SDWebImage.h -> {0}\n\
+             Missing.h: not found\n\
+             sdwebimage/sdwebimage.h -> {0}\n\
+             SDWebImage/SDWebImage.h -> {0}\n",
+            target_path
+        )
+    );
+}
+
+#[test]
+fn test_validate_exit_codes() {
+    let output = run_chmap([
+        "validate".as_ref(),
+        get_sdwebimage_binary_hmap_path().as_os_str(),
+    ]);
+    assert_exit_code(&output, EXIT_SUCCESS);
+
+    let output = run_chmap([
+        "validate".as_ref(),
+        get_sdwebimage_cut_off_binary_hmap_path().as_os_str(),
+    ]);
+    assert_exit_code(&output, EXIT_FAILURE);
+    assert!(!output.stdout.is_empty());
+}
+
+#[test]
+fn test_diff_exit_codes() {
+    let hmap_path = get_sdwebimage_binary_hmap_path();
+    let cut_off_hmap_path = get_sdwebimage_cut_off_binary_hmap_path();
+
+    let output = run_chmap([
+        "diff".as_ref(),
+        hmap_path.as_os_str(),
+        hmap_path.as_os_str(),
+    ]);
+    assert_exit_code(&output, EXIT_SUCCESS);
+    assert!(output.stdout.is_empty());
+
+    let output = run_chmap([
+        "diff".as_ref(),
+        hmap_path.as_os_str(),
+        cut_off_hmap_path.as_os_str(),
+    ]);
+    assert_exit_code(&output, EXIT_FAILURE);
+
+    let output = run_chmap([
+        "diff".as_ref(),
+        "--lenient".as_ref(),
+        hmap_path.as_os_str(),
+        cut_off_hmap_path.as_os_str(),
+    ]);
+    assert_exit_code(&output, EXIT_NEGATIVE_RESULT);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 43);
+    assert!(stdout.lines().all(|line| line.starts_with("- ")));
+}
+
+#[test]
+fn test_who_maps_exit_codes() {
+    let hmap_path = get_sdwebimage_binary_hmap_path();
+    let who_maps = |header_path: &str| {
+        run_chmap([
+            "who-maps".as_ref(),
+            header_path.as_ref(),
+            hmap_path.as_os_str(),
+        ])
+    };
+
+    // The header path gets normalized before matching targets.
+    let output = who_maps("/Users/milen/Desktop/SDWebImage/Core/../WebImage/SDWebImage.h");
+    assert_exit_code(&output, EXIT_SUCCESS);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with(": SDWebImage/SDWebImage.h\n"));
+
+    let output = who_maps("/Users/milen/Desktop/SDWebImage/WebImage/Missing.h");
+    assert_exit_code(&output, EXIT_NEGATIVE_RESULT);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_explain_lookup_exit_codes() {
+    let hmap_path = get_sdwebimage_binary_hmap_path();
+    let explain_lookup = |path: &Path, key: &str| {
+        run_chmap(["explain-lookup".as_ref(), path.as_os_str(), key.as_ref()])
+    };
+    assert_exit_code(
+        &explain_lookup(&hmap_path, "sdwebimage/sdwebimage.h"),
+        EXIT_SUCCESS,
+    );
+    assert_exit_code(
+        &explain_lookup(&hmap_path, "Missing.h"),
+        EXIT_NEGATIVE_RESULT,
+    );
+
+    // Corrupt buckets get explained instead of failing. The target of
+    // `SDWebImage/SDWebImage.h` in bucket 113 cannot be read, so it misses.
+    let temp_dir = get_temp_dir("explain-lookup-corrupt");
+    let corrupt_hmap_path = temp_dir.join("corrupt.hmap");
+    let mut bytes = std::fs::read(&hmap_path).unwrap();
+    let suffix_offset = 24 + 113 * 12 + 8;
+    let out_of_bounds_offset = bytes.len() as u32;
+    bytes[suffix_offset..suffix_offset + 4].copy_from_slice(&out_of_bounds_offset.to_le_bytes());
+    std::fs::write(&corrupt_hmap_path, &bytes).unwrap();
+
+    let output = explain_lookup(&corrupt_hmap_path, "SDWebImage/SDWebImage.h");
+    assert_exit_code(&output, EXIT_NEGATIVE_RESULT);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("its target cannot be read"), "{}", stdout);
+    assert_exit_code(
+        &explain_lookup(&corrupt_hmap_path, "SDWebImage/SDImageCache.h"),
+        EXIT_SUCCESS,
+    );
+
+    std::fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_set_keeps_symlink_and_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = get_temp_dir("set-symlink");
+    let hmap_path = temp_dir.join("target.hmap");
+    std::fs::copy(get_sdwebimage_binary_hmap_path(), &hmap_path).unwrap();
+    std::fs::set_permissions(&hmap_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let link_path = temp_dir.join("link.hmap");
+    std::os::unix::fs::symlink("target.hmap", &link_path).unwrap();
+
+    let output = run_chmap([
+        "set".as_ref(),
+        link_path.as_os_str(),
+        "New.h".as_ref(),
+        "/x/y/New.h".as_ref(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // The symlink still points to the edited file, which kept its mode.
+    assert!(std::fs::symlink_metadata(&link_path)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    let metadata = std::fs::metadata(&hmap_path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert!(lookup_target(&hmap_path, "New.h").contains("/x/y/New.h"));
+
+    let temp_files: Vec<_> = std::fs::read_dir(&temp_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .filter(|file_name| file_name.to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(temp_files.is_empty(), "{:?}", temp_files);
+
+    std::fs::remove_dir_all(&temp_dir).unwrap();
+}